@@ -0,0 +1,139 @@
+//! Compares rebuild and query cost of the spatial indexes at growth-like
+//! densities. Run with `cargo bench --bench spatial_index`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+
+use growth_simulation::config::Settings;
+use growth_simulation::spatial_index::*;
+use growth_simulation::vec2::{Point2, Vec2};
+
+const SIZES: [usize; 4] = [10_000, 50_000, 100_000, 500_000];
+
+/// Spreads points over the canvas, which the grid stores densely, like a
+/// grown path filling it.
+fn points(n: usize, settings: &Settings) -> Vec<Point2> {
+    let (width, height) = (settings.width as f64, settings.height as f64);
+    let mut rng = Pcg64::seed_from_u64(n as u64);
+    (0..n)
+        .map(|_| [rng.gen::<f64>() * width, rng.gen::<f64>() * height])
+        .collect()
+}
+
+fn settings() -> Settings {
+    let mut settings = Settings::new(1920, 1080);
+    settings.separation_distance = 10.0;
+    settings.attraction_distance = 10.0;
+    settings
+}
+
+fn bench_index(c: &mut Criterion) {
+    let settings = settings();
+    let mut group = c.benchmark_group("index");
+    group.sample_size(10);
+
+    for n in SIZES {
+        let points = points(n, &settings);
+        for (name, index_type) in [
+            ("rtree", SpatialIndexType::RTree),
+            ("grid", SpatialIndexType::Grid),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, n), &points, |b, points| {
+                b.iter(|| index_points(points.clone(), index_type, &settings))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_query(c: &mut Criterion) {
+    let settings = settings();
+    let mut group = c.benchmark_group("query_all");
+    group.sample_size(10);
+
+    for n in SIZES {
+        let points = points(n, &settings);
+        for (name, index_type) in [
+            ("rtree", SpatialIndexType::RTree),
+            ("grid", SpatialIndexType::Grid),
+        ] {
+            let index = index_points(points.clone(), index_type, &settings);
+            group.bench_with_input(BenchmarkId::new(name, n), &points, |b, points| {
+                b.iter(|| {
+                    points
+                        .iter()
+                        .map(|p| {
+                            index
+                                .locate_within_distance(
+                                    &Vec2::from_point2(p),
+                                    settings.separation_distance,
+                                )
+                                .len()
+                        })
+                        .sum::<usize>()
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Moves `share` of the points by up to `max_speed`, as one frame does, and
+/// compares applying those moves to each index against rebuilding it. This
+/// is what `prefers_rebuild` is tuned against.
+fn bench_apply(c: &mut Criterion) {
+    let settings = settings();
+    let mut group = c.benchmark_group("apply");
+    group.sample_size(10);
+
+    let n = 100_000;
+    let points = points(n, &settings);
+    for share in [0.01, 0.1, 0.25, 0.5, 1.0] {
+        let mut rng = Pcg64::seed_from_u64(7);
+        let mut changes = IndexChanges::new();
+        let mut moved = point_entries(points.clone());
+        for entry in moved.iter_mut() {
+            if rng.gen::<f64>() < share {
+                let from = Vec2::from_point2(&entry.position);
+                let to = from
+                    + Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+                        * settings.max_speed;
+                changes.move_node(entry.key.node, from, to);
+                entry.position = to.as_point2();
+            }
+        }
+
+        for (name, index_type) in [
+            ("rtree", SpatialIndexType::RTree),
+            ("grid", SpatialIndexType::Grid),
+        ] {
+            let label = format!("{}%", share * 100.0);
+            let fresh = || index_points(points.clone(), index_type, &settings);
+            group.bench_function(
+                BenchmarkId::new(format!("{}/incremental", name), &label),
+                |b| {
+                    b.iter_batched(
+                        fresh,
+                        |mut index| index.apply(changes.changes()),
+                        BatchSize::LargeInput,
+                    )
+                },
+            );
+            group.bench_function(BenchmarkId::new(format!("{}/rebuild", name), &label), |b| {
+                b.iter_batched(
+                    || (fresh(), moved.clone()),
+                    |(mut index, entries)| index.index(entries),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_index, bench_query, bench_apply);
+criterion_main!(benches);
@@ -0,0 +1,215 @@
+//! Headless renderer: runs a simulation from a config file and writes the
+//! result as SVG or PNG, optionally alongside a resumable snapshot. Build it
+//! with `--features cli`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use growth_simulation::config::Config;
+use growth_simulation::export::ExportOptions;
+use growth_simulation::simulation::Simulation;
+
+const USAGE: &str = "Usage: growth-sim [CONFIG] [OPTIONS]
+
+Runs a differential growth simulation without a browser.
+
+Arguments:
+  CONFIG                 Config file (.json or .toml)
+
+Options:
+  -n, --steps <N>        Number of update steps to run [default: 1000]
+  -o, --output <FILE>    Output image, .svg or .png [default: growth.svg]
+  -s, --snapshot <FILE>  Also save a snapshot (.json, anything else is binary)
+  -r, --resume <FILE>    Continue from a snapshot instead of a fresh setup
+      --seed <N>         Override the config seed
+      --frames <DIR>     Record frames into DIR as frame_00000.png, ...
+      --gif <FILE>       Record frames into an animated GIF
+      --bounds           Draw the bounds outline
+      --transparent      Leave out the background
+      --print-config     Print the default config as TOML and exit
+  -h, --help             Print this help";
+
+struct Args {
+    config: Option<String>,
+    steps: usize,
+    output: String,
+    snapshot: Option<String>,
+    resume: Option<String>,
+    seed: Option<u64>,
+    frames: Option<String>,
+    gif: Option<String>,
+    options: ExportOptions,
+}
+
+fn parse_args(mut raw: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut args = Args {
+        config: None,
+        steps: 1000,
+        output: "growth.svg".to_string(),
+        snapshot: None,
+        resume: None,
+        seed: None,
+        frames: None,
+        gif: None,
+        options: ExportOptions::new(),
+    };
+
+    while let Some(arg) = raw.next() {
+        let mut value = |name: &str| raw.next().ok_or(format!("missing value for {}", name));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(None);
+            }
+            "--print-config" => {
+                let config = toml::to_string(&Config::new(1200, 800)).unwrap();
+                print!("{}", config);
+                return Ok(None);
+            }
+            "-n" | "--steps" => {
+                args.steps = match value(&arg)?.parse() {
+                    Ok(steps) if steps > 0 => steps,
+                    _ => return Err("steps must be a positive integer".to_string()),
+                }
+            }
+            "-o" | "--output" => args.output = value(&arg)?,
+            "-s" | "--snapshot" => args.snapshot = Some(value(&arg)?),
+            "-r" | "--resume" => args.resume = Some(value(&arg)?),
+            "--seed" => {
+                args.seed = Some(
+                    value(&arg)?
+                        .parse()
+                        .map_err(|_| "seed must be a non-negative integer")?,
+                )
+            }
+            "--frames" => args.frames = Some(value(&arg)?),
+            "--gif" => args.gif = Some(value(&arg)?),
+            "--bounds" => args.options.include_bounds = true,
+            "--transparent" => args.options.include_background = false,
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if args.config.is_none() => args.config = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    if args.config.is_none() && args.resume.is_none() {
+        return Err("a config file or --resume snapshot is required".to_string());
+    }
+    // A snapshot carries its own RNG state, which a new seed can't change.
+    if args.seed.is_some() && args.resume.is_some() {
+        return Err("--seed cannot be combined with --resume".to_string());
+    }
+
+    Ok(Some(args))
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn load_config(path: &str) -> Result<Config, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    match extension(path).as_str() {
+        "toml" => toml::from_str(&contents).map_err(|e| format!("{}: {}", path, e)),
+        _ => serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path, e)),
+    }
+}
+
+fn load_snapshot(simulation: &mut Simulation, path: &str) -> Result<(), String> {
+    let result = if extension(path) == "json" {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        simulation.load_state(&json)
+    } else {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        simulation.load_state_binary(&bytes)
+    };
+    result.map_err(|e| format!("{}: {}", path, e))
+}
+
+fn write_file(path: &str, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut simulation = match &args.config {
+        Some(path) => Simulation::new(load_config(path)?),
+        None => Simulation::new(Config::new(1200, 800)),
+    };
+
+    match &args.resume {
+        Some(path) => load_snapshot(&mut simulation, path)?,
+        None => {
+            if let Some(seed) = args.seed {
+                simulation.config_mut().seed = seed;
+            }
+            simulation
+                .setup()
+                .map_err(|e| format!("invalid config: {}", e))?;
+        }
+    }
+
+    // Either output turns recording on, with the rest of the recording
+    // config taken from the config file.
+    if args.frames.is_some() || args.gif.is_some() {
+        simulation.config_mut().recording.recording = true;
+    }
+
+    for _ in 0..args.steps {
+        simulation.update();
+    }
+
+    match extension(&args.output).as_str() {
+        "svg" => write_file(&args.output, simulation.to_svg(args.options).as_bytes())?,
+        "png" => write_file(&args.output, &simulation.to_png(args.options))?,
+        ext => return Err(format!("unsupported output format '{}'", ext)),
+    }
+
+    let recording = simulation.recording();
+    if let Some(dir) = &args.frames {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+        for (name, png) in recording.to_png_sequence("frame_") {
+            write_file(&Path::new(dir).join(name).to_string_lossy(), &png)?;
+        }
+    }
+    if let Some(path) = &args.gif {
+        let frame_delay = simulation.config().recording.frame_delay;
+        let gif = recording
+            .to_gif(frame_delay)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        write_file(path, &gif)?;
+    }
+
+    if let Some(path) = &args.snapshot {
+        if extension(path) == "json" {
+            write_file(path, simulation.save_state().as_bytes())?;
+        } else {
+            write_file(path, &simulation.save_state_binary())?;
+        }
+    }
+
+    eprintln!(
+        "{} steps, {} nodes -> {}",
+        args.steps,
+        simulation.node_count(),
+        args.output
+    );
+
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args(env::args().skip(1)).and_then(|args| match args {
+        Some(args) => run(args),
+        None => Ok(()),
+    });
+
+    if let Err(err) = result {
+        eprintln!("error: {}\n\n{}", err, USAGE);
+        process::exit(1);
+    }
+}
@@ -1,6 +1,11 @@
 use crate::config::*;
+#[cfg(feature = "web")]
 use crate::draw::draw_path;
+#[cfg(feature = "web")]
+use crate::export::BOUNDS_COLOR;
 use crate::geometry::*;
+use crate::svg_path::{svg_shapes, SvgPathError};
+use crate::torus::Torus;
 use crate::vec2::*;
 
 pub trait Bounds {
@@ -8,7 +13,72 @@ pub trait Bounds {
         false
     }
 
-    fn draw(&self, _ctx: &web_sys::CanvasRenderingContext2d) {}
+    /// Signed distance to the edge of the bounds: negative inside, positive
+    /// outside. Infinite when there is no edge. The default measures the
+    /// distance to the outline.
+    fn distance(&self, point: Vec2) -> f64 {
+        let distance = outline_distance(&self.outline(), point);
+        if self.contains(point) {
+            -distance
+        } else {
+            distance
+        }
+    }
+
+    /// Unit vector along which `distance` grows fastest, i.e. pointing out
+    /// of the bounds. Zero where it is undefined.
+    fn gradient(&self, point: Vec2) -> Vec2 {
+        const STEP: f64 = 1e-3;
+        let dx = self.distance(point + Vec2::new(STEP, 0.0))
+            - self.distance(point - Vec2::new(STEP, 0.0));
+        let dy = self.distance(point + Vec2::new(0.0, STEP))
+            - self.distance(point - Vec2::new(0.0, STEP));
+        let mut gradient = Vec2::new(dx, dy);
+        if !gradient.x.is_finite() || !gradient.y.is_finite() {
+            return Vec2::new(0.0, 0.0);
+        }
+        gradient.normalize();
+        gradient
+    }
+
+    /// The torus nodes move on, if the bounds wrap around.
+    fn torus(&self) -> Option<Torus> {
+        None
+    }
+
+    /// Closed polylines tracing the edge of the bounds, used for rendering.
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        vec![]
+    }
+
+    #[cfg(feature = "web")]
+    fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        for points in self.outline() {
+            draw_path(ctx, &points, true, BOUNDS_COLOR, 1.0);
+        }
+    }
+}
+
+/// Distance from `point` to the closest of the `rings`.
+fn outline_distance(rings: &[Vec<Vec2>], point: Vec2) -> f64 {
+    rings
+        .iter()
+        .flat_map(|ring| {
+            ring.iter()
+                .zip(ring.iter().cycle().skip(1))
+                .map(|(a, b)| point.distance(&closest_point_on_segment(point, *a, *b)))
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Signed distance to the edge of an axis-aligned box.
+fn box_distance(point: Vec2, min: Vec2, max: Vec2) -> f64 {
+    let center = (min + max) / 2.0;
+    let half = (max - min) / 2.0;
+    let dx = (point.x - center.x).abs() - half.x;
+    let dy = (point.y - center.y).abs() - half.y;
+    let outside = Vec2::new(dx.max(0.0), dy.max(0.0)).length();
+    outside + dx.max(dy).min(0.0)
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -26,6 +96,30 @@ impl Bounds for ViewBounds {
     fn contains(&self, point: Vec2) -> bool {
         point.x > 0.0 && point.x < self.width && point.y > 0.0 && point.y < self.height
     }
+
+    fn distance(&self, point: Vec2) -> f64 {
+        box_distance(
+            point,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(self.width, self.height),
+        )
+    }
+}
+
+/// Wraps around instead of having an edge, so nothing is ever outside.
+#[derive(Copy, Clone, Debug)]
+pub struct TorusBounds {
+    pub torus: Torus,
+}
+
+impl Bounds for TorusBounds {
+    fn contains(&self, _point: Vec2) -> bool {
+        true
+    }
+
+    fn torus(&self) -> Option<Torus> {
+        Some(self.torus)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -50,9 +144,9 @@ impl RectBounds {
             max_x: top_corner.x,
             max_y: top_corner.y,
             points: vec![
-                bottom_corner.clone(),
+                bottom_corner,
                 Vec2::new(bottom_corner.x, top_corner.y),
-                top_corner.clone(),
+                top_corner,
                 Vec2::new(top_corner.x, bottom_corner.y),
             ],
         }
@@ -64,8 +158,16 @@ impl Bounds for RectBounds {
         point.x > self.min_x && point.x < self.max_x && point.y > self.min_y && point.y < self.max_y
     }
 
-    fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
-        draw_path(ctx, &self.points, true, "#888888");
+    fn distance(&self, point: Vec2) -> f64 {
+        box_distance(
+            point,
+            Vec2::new(self.min_x, self.min_y),
+            Vec2::new(self.max_x, self.max_y),
+        )
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        vec![self.points.clone()]
     }
 }
 
@@ -86,7 +188,7 @@ impl CircleBounds {
                 settings,
                 PolygonConfig {
                     n_sides: 40,
-                    radius: radius,
+                    radius,
                 },
             ),
             radius,
@@ -100,29 +202,504 @@ impl Bounds for CircleBounds {
         diff.length() < self.radius
     }
 
-    fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
-        draw_path(ctx, &self.points, true, "#888888");
-    }
-}
-
-pub fn get_bounds(config: Config) -> Box<dyn Bounds> {
-    match config.bounds.bounds_type {
-        BoundsType::None => Box::new(NoBounds {}),
-        BoundsType::View => Box::new(ViewBounds {
-            width: config.settings.width as f64,
-            height: config.settings.height as f64,
-        }),
-        BoundsType::Rect => Box::new(RectBounds::new(
-            config.settings.width as f64,
-            config.settings.height as f64,
-            config.bounds.rect_config.width,
-            config.bounds.rect_config.height,
+    fn distance(&self, point: Vec2) -> f64 {
+        point.distance(&self.center) - self.radius
+    }
+
+    fn gradient(&self, point: Vec2) -> Vec2 {
+        let mut gradient = point - self.center;
+        if gradient.length() == 0.0 {
+            return gradient;
+        }
+        gradient.normalize();
+        gradient
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        vec![self.points.clone()]
+    }
+}
+
+/// An arbitrary, possibly concave polygon with optional holes. A point is
+/// inside when it lies within an odd number of the rings.
+#[derive(Clone, Debug)]
+pub struct PolygonBounds {
+    rings: Vec<Vec<Vec2>>,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl PolygonBounds {
+    pub fn new(vertices: Vec<Vec2>, holes: Vec<Vec<Vec2>>) -> Self {
+        Self::from_rings(std::iter::once(vertices).chain(holes).collect())
+    }
+
+    /// Builds the bounds from closed rings, without telling outlines from
+    /// holes: nested rings alternate between inside and outside.
+    pub fn from_rings(rings: Vec<Vec<Vec2>>) -> Self {
+        let rings: Vec<Vec<Vec2>> = rings.into_iter().filter(|ring| ring.len() > 2).collect();
+
+        let mut min = Vec2::new(f64::INFINITY, f64::INFINITY);
+        let mut max = Vec2::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for point in rings.iter().flatten() {
+            min = Vec2::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vec2::new(max.x.max(point.x), max.y.max(point.y));
+        }
+
+        Self { rings, min, max }
+    }
+}
+
+impl Bounds for PolygonBounds {
+    fn contains(&self, point: Vec2) -> bool {
+        if point.x < self.min.x
+            || point.x > self.max.x
+            || point.y < self.min.y
+            || point.y > self.max.y
+        {
+            return false;
+        }
+
+        let crossings = self
+            .rings
+            .iter()
+            .filter(|ring| point_in_polygon(point, ring))
+            .count();
+        crossings % 2 == 1
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        self.rings.clone()
+    }
+}
+
+/// Inside any of the parts.
+pub struct UnionBounds {
+    parts: Vec<Box<dyn Bounds>>,
+    outline: Vec<Vec<Vec2>>,
+}
+
+impl UnionBounds {
+    pub fn new(parts: Vec<Box<dyn Bounds>>) -> Self {
+        let outline = clipped_outline(&parts, |part, point| {
+            !parts
+                .iter()
+                .enumerate()
+                .any(|(i, other)| i != part && other.contains(point))
+        });
+        Self { parts, outline }
+    }
+}
+
+impl Bounds for UnionBounds {
+    fn contains(&self, point: Vec2) -> bool {
+        self.parts.iter().any(|part| part.contains(point))
+    }
+
+    fn distance(&self, point: Vec2) -> f64 {
+        self.parts
+            .iter()
+            .map(|part| part.distance(point))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        self.outline.clone()
+    }
+}
+
+/// Inside all of the parts.
+pub struct IntersectionBounds {
+    parts: Vec<Box<dyn Bounds>>,
+    outline: Vec<Vec<Vec2>>,
+}
+
+impl IntersectionBounds {
+    pub fn new(parts: Vec<Box<dyn Bounds>>) -> Self {
+        let outline = clipped_outline(&parts, |part, point| {
+            parts
+                .iter()
+                .enumerate()
+                .all(|(i, other)| i == part || other.contains(point))
+        });
+        Self { parts, outline }
+    }
+}
+
+impl Bounds for IntersectionBounds {
+    fn contains(&self, point: Vec2) -> bool {
+        !self.parts.is_empty() && self.parts.iter().all(|part| part.contains(point))
+    }
+
+    fn distance(&self, point: Vec2) -> f64 {
+        match self.parts.is_empty() {
+            true => f64::INFINITY,
+            false => self
+                .parts
+                .iter()
+                .map(|part| part.distance(point))
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        self.outline.clone()
+    }
+}
+
+/// Inside `base` but not inside `cutout`.
+pub struct DifferenceBounds {
+    parts: [Box<dyn Bounds>; 2],
+    outline: Vec<Vec<Vec2>>,
+}
+
+impl DifferenceBounds {
+    pub fn new(base: Box<dyn Bounds>, cutout: Box<dyn Bounds>) -> Self {
+        let parts = [base, cutout];
+        let outline = clipped_outline(&parts, |part, point| match part {
+            0 => !parts[1].contains(point),
+            _ => parts[0].contains(point),
+        });
+        Self { parts, outline }
+    }
+}
+
+impl Bounds for DifferenceBounds {
+    fn contains(&self, point: Vec2) -> bool {
+        self.parts[0].contains(point) && !self.parts[1].contains(point)
+    }
+
+    fn distance(&self, point: Vec2) -> f64 {
+        self.parts[0]
+            .distance(point)
+            .max(-self.parts[1].distance(point))
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        self.outline.clone()
+    }
+}
+
+/// Everywhere the inner bounds are not. The outline stays the same.
+pub struct InvertedBounds {
+    inner: Box<dyn Bounds>,
+}
+
+impl InvertedBounds {
+    pub fn new(inner: Box<dyn Bounds>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Bounds for InvertedBounds {
+    fn contains(&self, point: Vec2) -> bool {
+        !self.inner.contains(point)
+    }
+
+    fn distance(&self, point: Vec2) -> f64 {
+        -self.inner.distance(point)
+    }
+
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        self.inner.outline()
+    }
+}
+
+/// The outline of a combination of `parts`: every part's outline is cut
+/// where it crosses another part's outline, and a piece survives when
+/// `keep(part, midpoint)` says it lies on the combined edge. The surviving
+/// pieces are then joined back into rings.
+fn clipped_outline(
+    parts: &[Box<dyn Bounds>],
+    keep: impl Fn(usize, Vec2) -> bool,
+) -> Vec<Vec<Vec2>> {
+    let outlines: Vec<Vec<Vec<Vec2>>> = parts.iter().map(|p| p.outline()).collect();
+    let mut rings = vec![];
+    let mut pieces = vec![];
+
+    for (part, part_rings) in outlines.iter().enumerate() {
+        let others: Vec<&Vec<Vec2>> = outlines
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != part)
+            .flat_map(|(_, rings)| rings.iter())
+            .collect();
+
+        for ring in part_rings.iter() {
+            let mut ring_pieces = vec![];
+            let mut run: Vec<Vec2> = vec![];
+            let mut whole = true;
+
+            for (i, a) in ring.iter().enumerate() {
+                let b = ring[(i + 1) % ring.len()];
+                let mut cuts = vec![0.0, 1.0];
+                for other in others.iter() {
+                    for (j, c) in other.iter().enumerate() {
+                        let d = other[(j + 1) % other.len()];
+                        if let Some(point) = segment_intersection(*a, b, *c, d) {
+                            cuts.push((point - *a).dot(&(b - *a)) / (b - *a).dot(&(b - *a)));
+                        }
+                    }
+                }
+                cuts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+                for t in cuts.windows(2) {
+                    let from = *a + (b - *a) * t[0];
+                    let to = *a + (b - *a) * t[1];
+                    if keep(part, (from + to) / 2.0) {
+                        if run.is_empty() {
+                            run.push(from);
+                        }
+                        run.push(to);
+                    } else {
+                        whole = false;
+                        if !run.is_empty() {
+                            ring_pieces.push(std::mem::take(&mut run));
+                        }
+                    }
+                }
+            }
+
+            if whole {
+                rings.push(ring.clone());
+            } else {
+                if !run.is_empty() {
+                    ring_pieces.push(run);
+                }
+                pieces.extend(ring_pieces);
+            }
+        }
+    }
+
+    rings.extend(join_pieces(pieces));
+    rings
+}
+
+/// Chains polyline pieces end to end, reversing them where needed, into
+/// rings. Pieces that can't be closed are returned as they are.
+fn join_pieces(mut pieces: Vec<Vec<Vec2>>) -> Vec<Vec<Vec2>> {
+    const TOLERANCE: f64 = 1e-6;
+    let mut rings = vec![];
+
+    while let Some(mut ring) = pieces.pop() {
+        loop {
+            let end = ring[ring.len() - 1];
+            if ring.len() > 2 && end.distance(&ring[0]) < TOLERANCE {
+                ring.pop();
+                break;
+            }
+
+            let next = pieces.iter().position(|p| {
+                p[0].distance(&end) < TOLERANCE || p[p.len() - 1].distance(&end) < TOLERANCE
+            });
+            match next {
+                Some(index) => {
+                    let mut piece = pieces.swap_remove(index);
+                    if piece[0].distance(&end) >= TOLERANCE {
+                        piece.reverse();
+                    }
+                    ring.extend(piece.into_iter().skip(1));
+                }
+                None => break,
+            }
+        }
+        rings.push(ring);
+    }
+
+    rings
+}
+
+/// Builds the bounds described by `shape`, recursing into combinations.
+/// Fails if any SVG path data in it doesn't parse.
+pub fn shape_bounds(
+    shape: &BoundsShape,
+    settings: &Settings,
+) -> Result<Box<dyn Bounds>, SvgPathError> {
+    let width = settings.width as f64;
+    let height = settings.height as f64;
+    let all = |shapes: &[BoundsShape]| -> Result<Vec<Box<dyn Bounds>>, SvgPathError> {
+        shapes.iter().map(|s| shape_bounds(s, settings)).collect()
+    };
+
+    Ok(match shape {
+        BoundsShape::View => Box::new(ViewBounds { width, height }),
+        BoundsShape::Rect(config) => {
+            Box::new(RectBounds::new(width, height, config.width, config.height))
+        }
+        BoundsShape::Circle(config) => Box::new(CircleBounds::new(
+            width / 2.0,
+            height / 2.0,
+            config.radius,
+            settings,
+        )),
+        BoundsShape::Polygon(config) => Box::new(PolygonBounds::new(
+            config.vertices.clone(),
+            config.holes.clone(),
         )),
-        BoundsType::Circle => Box::new(CircleBounds::new(
-            config.settings.width as f64 / 2.0,
-            config.settings.height as f64 / 2.0,
-            config.bounds.circle_config.radius,
-            &config.settings,
+        BoundsShape::Svg(config) => Box::new(PolygonBounds::from_rings(
+            svg_shapes(config, settings)?
+                .into_iter()
+                .map(|s| s.points)
+                .collect(),
         )),
+        BoundsShape::Union(shapes) => Box::new(UnionBounds::new(all(shapes)?)),
+        BoundsShape::Intersection(shapes) => Box::new(IntersectionBounds::new(all(shapes)?)),
+        BoundsShape::Difference(base, cutout) => Box::new(DifferenceBounds::new(
+            shape_bounds(base, settings)?,
+            shape_bounds(cutout, settings)?,
+        )),
+        BoundsShape::Invert(inner) => Box::new(InvertedBounds::new(shape_bounds(inner, settings)?)),
+    })
+}
+
+pub fn get_bounds(config: &Config) -> Result<Box<dyn Bounds>, SvgPathError> {
+    let bounds = &config.bounds;
+    let shape = match bounds.bounds_type {
+        BoundsType::None => return Ok(Box::new(NoBounds {})),
+        BoundsType::Torus => {
+            return Ok(Box::new(TorusBounds {
+                torus: Torus::new(config.settings.width as f64, config.settings.height as f64),
+            }))
+        }
+        BoundsType::View => BoundsShape::View,
+        BoundsType::Rect => BoundsShape::Rect(bounds.rect_config),
+        BoundsType::Circle => BoundsShape::Circle(bounds.circle_config),
+        BoundsType::Polygon => BoundsShape::Polygon(bounds.polygon_config.clone()),
+        BoundsType::Svg => BoundsShape::Svg(bounds.svg_config.clone()),
+        BoundsType::Composite => bounds.composite.clone(),
+    };
+    shape_bounds(&shape, &config.settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::*;
+
+    fn square(min: f64, max: f64) -> Vec<Vec2> {
+        vec![
+            Vec2::new(min, min),
+            Vec2::new(max, min),
+            Vec2::new(max, max),
+            Vec2::new(min, max),
+        ]
+    }
+
+    #[test]
+    fn bounds_polygon_with_hole() {
+        let bounds = PolygonBounds::new(square(0.0, 10.0), vec![square(4.0, 6.0)]);
+        assert!(bounds.contains(Vec2::new(2.0, 2.0)));
+        assert!(!bounds.contains(Vec2::new(5.0, 5.0)));
+        assert!(!bounds.contains(Vec2::new(12.0, 5.0)));
+        assert_eq!(bounds.outline().len(), 2);
+    }
+
+    #[test]
+    fn bounds_polygon_from_config() {
+        let mut config = Config::new(100, 100);
+        config.bounds.bounds_type = BoundsType::Polygon;
+        config.bounds.polygon_config.vertices = square(10.0, 20.0);
+        let bounds = get_bounds(&config).unwrap();
+        assert!(bounds.contains(Vec2::new(15.0, 15.0)));
+        assert!(!bounds.contains(Vec2::new(50.0, 50.0)));
+    }
+
+    fn square_bounds(min: f64, max: f64) -> Box<dyn Bounds> {
+        Box::new(PolygonBounds::new(square(min, max), vec![]))
+    }
+
+    fn ring_lengths(bounds: &dyn Bounds) -> Vec<usize> {
+        let mut lengths: Vec<usize> = bounds.outline().iter().map(|r| r.len()).collect();
+        lengths.sort_unstable();
+        lengths
+    }
+
+    #[test]
+    fn bounds_union() {
+        let bounds = UnionBounds::new(vec![square_bounds(0.0, 10.0), square_bounds(5.0, 15.0)]);
+        assert!(bounds.contains(Vec2::new(2.0, 2.0)));
+        assert!(bounds.contains(Vec2::new(12.0, 12.0)));
+        assert!(!bounds.contains(Vec2::new(12.0, 2.0)));
+
+        // One L-shaped ring with the two crossing points as extra corners.
+        assert_eq!(ring_lengths(&bounds), vec![8]);
+        let outline = bounds.outline();
+        assert!(outline[0].contains(&Vec2::new(10.0, 5.0)));
+        assert!(outline[0].contains(&Vec2::new(5.0, 10.0)));
+        assert!(!outline[0].contains(&Vec2::new(10.0, 10.0)));
+    }
+
+    #[test]
+    fn bounds_intersection() {
+        let bounds =
+            IntersectionBounds::new(vec![square_bounds(0.0, 10.0), square_bounds(5.0, 15.0)]);
+        assert!(bounds.contains(Vec2::new(7.0, 7.0)));
+        assert!(!bounds.contains(Vec2::new(2.0, 2.0)));
+        assert_eq!(ring_lengths(&bounds), vec![4]);
+        assert!(!IntersectionBounds::new(vec![]).contains(Vec2::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn bounds_difference() {
+        // A square frame keeps both outlines whole.
+        let frame = DifferenceBounds::new(square_bounds(0.0, 10.0), square_bounds(4.0, 6.0));
+        assert!(frame.contains(Vec2::new(2.0, 2.0)));
+        assert!(!frame.contains(Vec2::new(5.0, 5.0)));
+        assert_eq!(ring_lengths(&frame), vec![4, 4]);
+
+        // Biting off a corner leaves a single ring.
+        let bitten = DifferenceBounds::new(square_bounds(0.0, 10.0), square_bounds(5.0, 15.0));
+        assert!(!bitten.contains(Vec2::new(7.0, 7.0)));
+        assert_eq!(ring_lengths(&bitten), vec![6]);
+    }
+
+    #[test]
+    fn bounds_inverted() {
+        let bounds = InvertedBounds::new(square_bounds(0.0, 10.0));
+        assert!(!bounds.contains(Vec2::new(5.0, 5.0)));
+        assert!(bounds.contains(Vec2::new(20.0, 5.0)));
+        assert_eq!(ring_lengths(&bounds), vec![4]);
+    }
+
+    #[test]
+    fn bounds_composite_ring_from_config() {
+        let mut config = Config::new(200, 200);
+        config.bounds.bounds_type = BoundsType::Composite;
+        config.bounds.composite = serde_json::from_str(
+            r#"{"Difference": [{"Circle": {"radius": 80}}, {"Circle": {"radius": 40}}]}"#,
+        )
+        .unwrap();
+        let bounds = get_bounds(&config).unwrap();
+        assert!(bounds.contains(Vec2::new(160.0, 100.0)));
+        assert!(!bounds.contains(Vec2::new(100.0, 100.0)));
+        assert!(!bounds.contains(Vec2::new(190.0, 100.0)));
+        assert_eq!(bounds.outline().len(), 2);
+    }
+
+    #[test]
+    fn bounds_signed_distance() {
+        let view = ViewBounds {
+            width: 10.0,
+            height: 10.0,
+        };
+        assert_eq!(view.distance(Vec2::new(2.0, 5.0)), -2.0);
+        assert_eq!(view.distance(Vec2::new(13.0, 14.0)), 5.0);
+        assert_eq!(view.gradient(Vec2::new(2.0, 5.0)), Vec2::new(-1.0, 0.0));
+
+        // Falls back to the outline for polygons.
+        let square = square_bounds(0.0, 10.0);
+        assert_eq!(square.distance(Vec2::new(5.0, 3.0)), -3.0);
+        assert_eq!(square.distance(Vec2::new(5.0, 12.0)), 2.0);
+
+        let frame = DifferenceBounds::new(square_bounds(0.0, 10.0), square_bounds(4.0, 6.0));
+        assert_eq!(frame.distance(Vec2::new(5.0, 5.0)), 1.0);
+        assert_eq!(frame.distance(Vec2::new(5.0, 2.0)), -2.0);
+
+        let union = UnionBounds::new(vec![square_bounds(0.0, 10.0), square_bounds(20.0, 30.0)]);
+        assert_eq!(union.distance(Vec2::new(15.0, 5.0)), 5.0);
+        assert_eq!(
+            InvertedBounds::new(square).distance(Vec2::new(5.0, 3.0)),
+            3.0
+        );
+        assert_eq!(NoBounds {}.distance(Vec2::new(5.0, 3.0)), f64::INFINITY);
     }
 }
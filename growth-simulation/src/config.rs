@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use crate::noise::NoiseType;
+use crate::spatial_index::SpatialIndexType;
+use crate::style::StyleConfig;
+use crate::vec2::Vec2;
+
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
@@ -16,6 +21,48 @@ pub struct Settings {
     pub max_edge_length: f64,
     pub min_edge_length: f64,
     pub injection_probability: f64,
+    /// Where injected nodes go.
+    #[serde(default)]
+    pub injection_strategy: InjectionStrategy,
+    /// How many times per frame a path tries to inject a node, each try
+    /// succeeding with `injection_probability`.
+    #[serde(default = "default_injection_count")]
+    pub injection_count: u32,
+    /// Limits attraction and separation to this many of the closest nodes
+    /// in range. Zero means no limit.
+    #[serde(default)]
+    pub max_neighbors: u32,
+    /// How many nodes on each side along its own path a node ignores when
+    /// attracting and repelling. The node itself is always ignored.
+    #[serde(default)]
+    pub excluded_neighbors: u32,
+    #[serde(default)]
+    pub separation_mode: SeparationMode,
+    #[serde(default)]
+    pub wall_mode: WallMode,
+    /// Strength of the push back inside in `WallMode::Repel`.
+    #[serde(default = "default_wall_force")]
+    pub wall_force: f64,
+    /// How far inside the bounds the push in `WallMode::Repel` starts.
+    #[serde(default = "default_wall_distance")]
+    pub wall_distance: f64,
+    #[serde(default)]
+    pub noise_type: NoiseType,
+    /// Pushes nodes along a noise field. Zero turns the noise off.
+    #[serde(default)]
+    pub noise_strength: f64,
+    /// Spatial frequency of the noise; smaller values give broader swirls.
+    /// On a torus it is rounded so the noise tiles across the view.
+    #[serde(default = "default_noise_scale")]
+    pub noise_scale: f64,
+    /// Layers of finer detail on top of the base noise.
+    #[serde(default = "default_noise_octaves")]
+    pub noise_octaves: u32,
+    /// How fast the noise changes per frame.
+    #[serde(default = "default_noise_speed")]
+    pub noise_speed: f64,
+    #[serde(default)]
+    pub noise_seed: u32,
 }
 
 #[wasm_bindgen]
@@ -34,20 +81,130 @@ impl Settings {
             max_edge_length: 5.0,
             min_edge_length: 1.0,
             injection_probability: 0.5,
+            injection_strategy: InjectionStrategy::Uniform,
+            injection_count: 1,
+            max_neighbors: 0,
+            excluded_neighbors: 0,
+            separation_mode: SeparationMode::Nodes,
+            wall_mode: WallMode::Freeze,
+            wall_force: 1.0,
+            wall_distance: 10.0,
+            noise_type: NoiseType::Curl,
+            noise_strength: 0.0,
+            noise_scale: 0.01,
+            noise_octaves: 3,
+            noise_speed: 0.01,
+            noise_seed: 0,
         }
     }
 }
 
+fn default_injection_count() -> u32 {
+    1
+}
+
+fn default_wall_force() -> f64 {
+    1.0
+}
+
+fn default_wall_distance() -> f64 {
+    10.0
+}
+
+fn default_noise_scale() -> f64 {
+    0.01
+}
+
+fn default_noise_octaves() -> u32 {
+    3
+}
+
+fn default_noise_speed() -> f64 {
+    0.01
+}
+
+/// What the separation force pushes nodes away from.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SeparationMode {
+    /// Other nodes.
+    #[default]
+    Nodes,
+    /// The closest points of nearby edges. Moves that would make two edges
+    /// cross are shortened or rejected, so paths never self-intersect.
+    Edges,
+}
+
+/// What happens to a node that reaches the edge of the bounds.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum WallMode {
+    /// The node stops moving for good once it leaves the bounds.
+    #[default]
+    Freeze,
+    /// A force that ramps up over `wall_distance` pushes the node back
+    /// inside before it gets there.
+    Repel,
+    /// The node is mirrored back inside and its outward velocity flipped.
+    Reflect,
+    /// The node is moved back onto the edge and its outward velocity dropped.
+    Clamp,
+}
+
+/// How the edge that an injected node splits is picked.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum InjectionStrategy {
+    /// Every edge is equally likely.
+    #[default]
+    Uniform,
+    /// Edges where the path turns sharply are more likely, which
+    /// exaggerates existing bumps.
+    Curvature,
+    /// Longer edges are more likely, which evens out the node spacing.
+    Tension,
+    /// Edges between older nodes are more likely.
+    Age,
+}
+
+/// What `update` keeps of each recorded frame.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureType {
+    /// The styled paths, rasterized on export. Small, but slower to export.
+    #[default]
+    Vector,
+    /// The rendered image.
+    Raster,
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RecordingConfig {
     pub recording: bool,
+    /// Captures every `interval` frames. Zero counts as one.
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    #[serde(default)]
+    pub capture_type: CaptureType,
+    /// Stops capturing once this many frames are held. Zero means no limit.
+    #[serde(default)]
+    pub max_frames: u32,
+    /// Time each frame is shown in an exported GIF, in milliseconds.
+    #[serde(default = "default_frame_delay")]
+    pub frame_delay: u32,
 }
 
 #[wasm_bindgen]
 impl RecordingConfig {
     pub fn new() -> Self {
-        Self { recording: false }
+        Self {
+            recording: false,
+            interval: default_interval(),
+            capture_type: CaptureType::Vector,
+            max_frames: 0,
+            frame_delay: default_frame_delay(),
+        }
     }
 }
 
@@ -57,6 +214,14 @@ impl Default for RecordingConfig {
     }
 }
 
+fn default_interval() -> u32 {
+    1
+}
+
+fn default_frame_delay() -> u32 {
+    40
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PolygonConfig {
@@ -86,13 +251,42 @@ pub enum InitializationType {
     HorizontalLine,
     VerticalLine,
     Polygon,
+    Svg,
+}
+
+/// Shapes given as SVG path data, e.g. a logo or a letterform.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SvgConfig {
+    /// The `d` attribute of an SVG `<path>`.
+    pub path_data: String,
+    /// Scales the shapes so their larger side spans this many pixels and
+    /// centers them on the canvas. Zero keeps the path's own coordinates.
+    pub size: f64,
+}
+
+impl SvgConfig {
+    pub fn new() -> Self {
+        Self {
+            path_data: String::new(),
+            size: 300.0,
+        }
+    }
+}
+
+impl Default for SvgConfig {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct InitializationConfig {
     pub init_type: InitializationType,
     pub polygon_config: PolygonConfig,
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub svg_config: SvgConfig,
 }
 
 #[wasm_bindgen]
@@ -101,6 +295,7 @@ impl InitializationConfig {
         Self {
             init_type: InitializationType::Polygon,
             polygon_config: PolygonConfig::new(),
+            svg_config: SvgConfig::new(),
         }
     }
 }
@@ -131,15 +326,55 @@ pub enum BoundsType {
     View,
     Rect,
     Circle,
+    Polygon,
+    Svg,
+    Composite,
+    /// The view with opposite edges glued together, for tileable output.
+    Torus,
+}
+
+/// A region built from the basic shapes and boolean combinations of them,
+/// e.g. a ring as a circle minus a smaller circle. Rects and circles are
+/// centered on the canvas.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum BoundsShape {
+    #[default]
+    View,
+    Rect(RectConfig),
+    Circle(CircleConfig),
+    Polygon(PolygonBoundsConfig),
+    Svg(SvgConfig),
+    Union(Vec<BoundsShape>),
+    Intersection(Vec<BoundsShape>),
+    Difference(Box<BoundsShape>, Box<BoundsShape>),
+    Invert(Box<BoundsShape>),
+}
+
+/// A polygon in canvas coordinates. Vertices may form a concave outline and
+/// each hole is cut out of it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolygonBoundsConfig {
+    pub vertices: Vec<Vec2>,
+    #[serde(default)]
+    pub holes: Vec<Vec<Vec2>>,
 }
 
 #[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BoundsConfig {
     pub bounds_type: BoundsType,
     pub draw: bool,
     pub circle_config: CircleConfig,
     pub rect_config: RectConfig,
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub polygon_config: PolygonBoundsConfig,
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub svg_config: SvgConfig,
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub composite: BoundsShape,
 }
 
 impl BoundsConfig {
@@ -152,6 +387,9 @@ impl BoundsConfig {
                 width: 100.0,
                 height: 100.0,
             },
+            polygon_config: PolygonBoundsConfig::default(),
+            svg_config: SvgConfig::new(),
+            composite: BoundsShape::default(),
         }
     }
 }
@@ -162,13 +400,90 @@ impl Default for BoundsConfig {
     }
 }
 
+/// How a density map loaded into the simulation changes growth.
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DensityConfig {
+    /// Where the map is black, distances are multiplied by this. Where it is
+    /// white, the injection probability is.
+    pub dark_scale: f64,
+    /// Scales `max_edge_length` and `min_edge_length`.
+    pub scale_edge_length: bool,
+    pub scale_separation: bool,
+    pub scale_injection: bool,
+}
+
+#[wasm_bindgen]
+impl DensityConfig {
+    pub fn new() -> Self {
+        Self {
+            dark_scale: 0.25,
+            scale_edge_length: true,
+            scale_separation: true,
+            scale_injection: true,
+        }
+    }
+}
+
+impl Default for DensityConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Physical output settings for pen plotters. Lengths are in millimeters and
+/// feed rates in millimeters per minute.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlotterConfig {
+    pub page_width: f64,
+    pub page_height: f64,
+    pub margin: f64,
+    pub feed_rate: f64,
+    pub pen_up_z: f64,
+    pub pen_down_z: f64,
+}
+
+#[wasm_bindgen]
+impl PlotterConfig {
+    pub fn new() -> Self {
+        Self {
+            page_width: 297.0,
+            page_height: 210.0,
+            margin: 10.0,
+            feed_rate: 1500.0,
+            pen_up_z: 5.0,
+            pen_down_z: 0.0,
+        }
+    }
+}
+
+impl Default for PlotterConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub settings: Settings,
     pub initialization: InitializationConfig,
     pub bounds: BoundsConfig,
     pub recording: RecordingConfig,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub spatial_index: SpatialIndexType,
+    #[serde(default)]
+    pub density: DensityConfig,
+    /// Restricts node injection to the inside of this shape.
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub injection_mask: Option<BoundsShape>,
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub style: StyleConfig,
 }
 
 #[wasm_bindgen]
@@ -179,6 +494,11 @@ impl Config {
             initialization: InitializationConfig::new(),
             bounds: BoundsConfig::new(),
             recording: RecordingConfig::new(),
+            seed: 0,
+            spatial_index: SpatialIndexType::Grid,
+            density: DensityConfig::new(),
+            injection_mask: None,
+            style: StyleConfig::new(),
         }
     }
 }
@@ -0,0 +1,301 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DensityConfig, Settings};
+use crate::torus::Torus;
+use crate::vec2::Vec2;
+
+/// A grayscale image stretched over the canvas. Dark regions grow dense
+/// and light regions sparse.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "DensityMapData")]
+pub struct DensityMap {
+    width: u32,
+    height: u32,
+    /// Row-major, from 0 for white to 1 for black.
+    darkness: Vec<f32>,
+}
+
+/// A density map as stored, checked by `DensityMap::new` on the way in.
+#[derive(Deserialize)]
+struct DensityMapData {
+    width: u32,
+    height: u32,
+    darkness: Vec<f32>,
+}
+
+impl TryFrom<DensityMapData> for DensityMap {
+    type Error = DensityMapError;
+
+    fn try_from(data: DensityMapData) -> Result<Self, Self::Error> {
+        Self::new(data.width, data.height, data.darkness)
+    }
+}
+
+#[derive(Debug)]
+pub enum DensityMapError {
+    Decode(png::DecodingError),
+    Empty,
+    /// The number of values doesn't match the width and height.
+    SizeMismatch,
+}
+
+impl fmt::Display for DensityMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DensityMapError::Decode(err) => write!(f, "invalid density map: {}", err),
+            DensityMapError::Empty => write!(f, "density map has no pixels"),
+            DensityMapError::SizeMismatch => {
+                write!(f, "density map size doesn't match its pixel count")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DensityMapError {}
+
+impl DensityMap {
+    /// `darkness` holds `width * height` values between 0 and 1, row by row.
+    pub fn new(width: u32, height: u32, darkness: Vec<f32>) -> Result<Self, DensityMapError> {
+        if width == 0 || height == 0 {
+            return Err(DensityMapError::Empty);
+        }
+        let pixels = (width as usize).checked_mul(height as usize);
+        if pixels != Some(darkness.len()) {
+            return Err(DensityMapError::SizeMismatch);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            darkness,
+        })
+    }
+
+    /// Decodes a PNG of any color type. Colors are reduced to their luma and
+    /// transparent pixels count as white.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, DensityMapError> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(DensityMapError::Decode)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(DensityMapError::Decode)?;
+
+        let channels = info.color_type.samples();
+        let darkness = buffer[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|pixel| {
+                let (luma, alpha) = match pixel {
+                    [gray] => (*gray as f32, 255.0),
+                    [gray, alpha] => (*gray as f32, *alpha as f32),
+                    [r, g, b] => (luma(*r, *g, *b), 255.0),
+                    [r, g, b, alpha, ..] => (luma(*r, *g, *b), *alpha as f32),
+                    [] => (255.0, 255.0),
+                };
+                let alpha = alpha / 255.0;
+                1.0 - (luma / 255.0 * alpha + (1.0 - alpha))
+            })
+            .collect();
+
+        Self::new(info.width, info.height, darkness)
+    }
+
+    fn pixel(&self, x: i64, y: i64, periodic: bool) -> f64 {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let (x, y) = if periodic {
+            (x.rem_euclid(width), y.rem_euclid(height))
+        } else {
+            (x.clamp(0, width - 1), y.clamp(0, height - 1))
+        };
+        self.darkness[(y * width + x) as usize] as f64
+    }
+
+    /// Darkness at `point` on a `width`×`height` canvas, interpolated
+    /// between pixel centers. Past the edge pixels it stays at their value.
+    pub fn darkness(&self, point: Vec2, width: f64, height: f64) -> f64 {
+        self.sample(point, width, height, false)
+    }
+
+    /// Like `darkness`, but the map tiles the plane, so the edges blend into
+    /// the opposite side instead of stopping at the edge pixels.
+    pub fn darkness_periodic(&self, point: Vec2, width: f64, height: f64) -> f64 {
+        self.sample(point, width, height, true)
+    }
+
+    fn sample(&self, point: Vec2, width: f64, height: f64, periodic: bool) -> f64 {
+        let x = point.x / width * self.width as f64 - 0.5;
+        let y = point.y / height * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let pixel = |x, y| self.pixel(x, y, periodic);
+        let top = pixel(x0, y0) * (1.0 - tx) + pixel(x0 + 1, y0) * tx;
+        let bottom = pixel(x0, y0 + 1) * (1.0 - tx) + pixel(x0 + 1, y0 + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+fn luma(r: u8, g: u8, b: u8) -> f32 {
+    0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32
+}
+
+/// A density map together with how it is applied.
+#[derive(Clone, Copy, Debug)]
+pub struct Density<'a> {
+    pub map: &'a DensityMap,
+    pub config: DensityConfig,
+    /// Set when the view wraps around, so the map wraps with it.
+    pub torus: Option<Torus>,
+}
+
+impl<'a> Density<'a> {
+    /// `settings` as they apply at `point`. Distances shrink towards
+    /// `dark_scale` times their value as the map gets darker, while the
+    /// injection probability drops to `dark_scale` times its value where the
+    /// map is white.
+    pub fn settings_at(&self, settings: &Settings, point: Vec2) -> Settings {
+        let (width, height) = (settings.width as f64, settings.height as f64);
+        let darkness = match &self.torus {
+            Some(torus) => self.map.darkness_periodic(torus.wrap(point), width, height),
+            None => self.map.darkness(point, width, height),
+        };
+        let config = &self.config;
+        let distance_scale = 1.0 + (config.dark_scale - 1.0) * darkness;
+        let injection_scale = config.dark_scale + (1.0 - config.dark_scale) * darkness;
+
+        let mut local = *settings;
+        if config.scale_edge_length {
+            local.max_edge_length *= distance_scale;
+            local.min_edge_length *= distance_scale;
+        }
+        if config.scale_separation {
+            local.separation_distance *= distance_scale;
+        }
+        if config.scale_injection {
+            local.injection_probability *= injection_scale;
+        }
+        local
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::density::*;
+
+    fn encode(color_type: png::ColorType, width: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        {
+            let height = data.len() as u32 / width / color_type.samples() as u32;
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(color_type);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn density_from_png() {
+        let gray = DensityMap::from_png(&encode(png::ColorType::Grayscale, 2, &[0, 255])).unwrap();
+        assert_eq!(gray.darkness, vec![1.0, 0.0]);
+
+        // Transparent black reads as white.
+        let rgba = encode(png::ColorType::Rgba, 2, &[255, 255, 255, 255, 0, 0, 0, 0]);
+        assert_eq!(
+            DensityMap::from_png(&rgba).unwrap().darkness,
+            vec![0.0, 0.0]
+        );
+
+        assert!(matches!(
+            DensityMap::from_png(&[1, 2, 3]),
+            Err(DensityMapError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn density_map_checks_size() {
+        assert!(matches!(
+            DensityMap::new(0, 1, vec![]),
+            Err(DensityMapError::Empty)
+        ));
+        assert!(matches!(
+            DensityMap::new(u32::MAX, u32::MAX, vec![1.0]),
+            Err(DensityMapError::SizeMismatch)
+        ));
+
+        let map = DensityMap::new(2, 1, vec![1.0, 0.0]).unwrap();
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<DensityMap>(&json).unwrap(), map);
+
+        for json in &[
+            r#"{"width":3,"height":1,"darkness":[1.0,0.0]}"#,
+            r#"{"width":0,"height":0,"darkness":[]}"#,
+        ] {
+            assert!(serde_json::from_str::<DensityMap>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn density_darkness_interpolates() {
+        let map = DensityMap::new(2, 1, vec![1.0, 0.0]).unwrap();
+        assert_eq!(map.darkness(Vec2::new(10.0, 5.0), 40.0, 10.0), 1.0);
+        assert_eq!(map.darkness(Vec2::new(20.0, 5.0), 40.0, 10.0), 0.5);
+        assert_eq!(map.darkness(Vec2::new(40.0, 5.0), 40.0, 10.0), 0.0);
+        assert_eq!(map.darkness_periodic(Vec2::new(40.0, 5.0), 40.0, 10.0), 0.5);
+    }
+
+    #[test]
+    fn density_wraps_on_torus() {
+        let map = DensityMap::new(4, 2, vec![1.0, 0.5, 0.0, 0.25, 0.0, 1.0, 0.5, 0.75]).unwrap();
+        let density = Density {
+            map: &map,
+            config: DensityConfig::new(),
+            torus: Some(Torus::new(40.0, 10.0)),
+        };
+        let settings = Settings::new(40, 10);
+
+        for &(x, y) in &[(0.0, 0.0), (1.0, 9.5), (39.0, 3.0), (17.5, 6.0)] {
+            let at = |x, y| density.settings_at(&settings, Vec2::new(x, y));
+            let here = at(x, y);
+            assert_eq!(at(x + 40.0, y), here);
+            assert_eq!(at(x - 40.0, y + 10.0), here);
+        }
+        // Between the last and first pixel columns the map blends the two.
+        assert_eq!(
+            map.darkness_periodic(Vec2::new(0.0, 2.5), 40.0, 10.0),
+            0.625
+        );
+        assert_eq!(map.darkness(Vec2::new(0.0, 2.5), 40.0, 10.0), 1.0);
+    }
+
+    #[test]
+    fn density_settings_at() {
+        let map = DensityMap::new(2, 1, vec![1.0, 0.0]).unwrap();
+        let density = Density {
+            map: &map,
+            config: DensityConfig::new(),
+            torus: None,
+        };
+        let settings = Settings::new(40, 10);
+
+        let dark = density.settings_at(&settings, Vec2::new(0.0, 5.0));
+        assert_eq!(
+            dark.max_edge_length,
+            settings.max_edge_length * density.config.dark_scale
+        );
+        assert_eq!(dark.injection_probability, settings.injection_probability);
+
+        let light = density.settings_at(&settings, Vec2::new(40.0, 5.0));
+        assert_eq!(light.separation_distance, settings.separation_distance);
+        assert_eq!(
+            light.injection_probability,
+            settings.injection_probability * density.config.dark_scale
+        );
+    }
+}
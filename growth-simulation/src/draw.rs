@@ -5,11 +5,12 @@ pub fn draw_path(
     points: &[Vec2],
     cyclic: bool,
     color: &str,
+    width: f64,
 ) {
     ctx.save();
     ctx.begin_path();
-    ctx.set_line_width(1.0);
-    ctx.set_stroke_style(&color.into());
+    ctx.set_line_width(width);
+    ctx.set_stroke_style_str(color);
 
     for (index, point) in points.iter().enumerate() {
         let mut prev_index = index;
@@ -32,3 +33,19 @@ pub fn draw_path(
 
     ctx.restore();
 }
+
+pub fn draw_marker(
+    ctx: &web_sys::CanvasRenderingContext2d,
+    center: Vec2,
+    radius: f64,
+    color: &str,
+) {
+    ctx.save();
+    ctx.begin_path();
+    ctx.set_line_width(1.0);
+    ctx.set_stroke_style_str(color);
+    ctx.arc(center.x, center.y, radius, 0.0, std::f64::consts::PI * 2.0)
+        .unwrap();
+    ctx.stroke();
+    ctx.restore();
+}
@@ -0,0 +1,27 @@
+pub const BACKGROUND_COLOR: &str = "#000000";
+pub const PATH_COLOR: &str = "#ffffff";
+pub const BOUNDS_COLOR: &str = "#888888";
+pub const CROSSING_COLOR: &str = "#ff3333";
+pub const FIELD_COLOR: &str = "#33aaff";
+
+/// Options shared by the vector and raster exporters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportOptions {
+    pub include_bounds: bool,
+    pub include_background: bool,
+}
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        Self {
+            include_bounds: false,
+            include_background: true,
+        }
+    }
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
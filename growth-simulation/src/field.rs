@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::torus::Torus;
+use crate::vec2::Vec2;
+
+/// How a field weakens between its center and the edge of its radius.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Falloff {
+    /// Full strength everywhere in range.
+    Constant,
+    /// Fades evenly to nothing at the radius.
+    #[default]
+    Linear,
+    /// Fades quickly away from the center.
+    Quadratic,
+}
+
+impl Falloff {
+    /// The share of full strength at `t`, the distance as a fraction of the
+    /// radius.
+    pub fn weight(&self, t: f64) -> f64 {
+        if t >= 1.0 {
+            return 0.0;
+        }
+
+        let t = t.max(0.0);
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum FieldKind {
+    /// Pulls nodes towards the field's position.
+    Attractor,
+    /// Pushes nodes away from the field's position.
+    Repeller,
+    /// Pushes nodes along `direction`.
+    Flow { direction: Vec2 },
+}
+
+/// A scene object that pushes every node within `radius` of `position`,
+/// independent of the paths themselves. A radius of 0 reaches everywhere at
+/// full strength.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ForceField {
+    pub kind: FieldKind,
+    pub position: Vec2,
+    pub strength: f64,
+    pub radius: f64,
+    #[serde(default)]
+    pub falloff: Falloff,
+}
+
+impl ForceField {
+    pub fn attractor(position: Vec2, strength: f64, radius: f64) -> Self {
+        Self {
+            kind: FieldKind::Attractor,
+            position,
+            strength,
+            radius,
+            falloff: Falloff::default(),
+        }
+    }
+
+    pub fn repeller(position: Vec2, strength: f64, radius: f64) -> Self {
+        Self {
+            kind: FieldKind::Repeller,
+            ..Self::attractor(position, strength, radius)
+        }
+    }
+
+    pub fn flow(position: Vec2, direction: Vec2, strength: f64, radius: f64) -> Self {
+        Self {
+            kind: FieldKind::Flow { direction },
+            ..Self::attractor(position, strength, radius)
+        }
+    }
+
+    /// The force on a node at `point`. On a torus the field acts through
+    /// whichever copy of its position is closest.
+    pub fn force_at(&self, point: Vec2, torus: Option<&Torus>) -> Vec2 {
+        let offset = match torus {
+            Some(torus) => torus.offset(point, self.position),
+            None => self.position - point,
+        };
+        let distance = offset.length();
+        let weight = match self.radius {
+            r if r > 0.0 => self.falloff.weight(distance / r),
+            _ => 1.0,
+        };
+        if weight == 0.0 {
+            return Vec2::new(0.0, 0.0);
+        }
+
+        let mut direction = match self.kind {
+            FieldKind::Attractor => offset,
+            FieldKind::Repeller => offset * -1.0,
+            FieldKind::Flow { direction } => direction,
+        };
+        direction.normalize();
+        direction * (self.strength * weight)
+    }
+}
+
+/// The combined force of `fields` on a node at `point`.
+pub fn field_force(fields: &[ForceField], point: Vec2, torus: Option<&Torus>) -> Vec2 {
+    fields.iter().fold(Vec2::new(0.0, 0.0), |total, field| {
+        total + field.force_at(point, torus)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::*;
+
+    #[test]
+    fn field_falloff() {
+        assert_eq!(Falloff::Constant.weight(0.9), 1.0);
+        assert_eq!(Falloff::Linear.weight(0.25), 0.75);
+        assert_eq!(Falloff::Quadratic.weight(0.5), 0.25);
+        assert_eq!(Falloff::Constant.weight(1.0), 0.0);
+    }
+
+    #[test]
+    fn field_force_at() {
+        let center = Vec2::new(50.0, 50.0);
+        let point = Vec2::new(60.0, 50.0);
+
+        let attractor = ForceField::attractor(center, 2.0, 20.0);
+        assert_eq!(attractor.force_at(point, None), Vec2::new(-1.0, 0.0));
+        assert_eq!(
+            attractor.force_at(Vec2::new(80.0, 50.0), None),
+            Vec2::new(0.0, 0.0)
+        );
+
+        let repeller = ForceField::repeller(center, 2.0, 20.0);
+        assert_eq!(repeller.force_at(point, None), Vec2::new(1.0, 0.0));
+
+        let flow = ForceField::flow(center, Vec2::new(0.0, 3.0), 1.0, 0.0);
+        assert_eq!(
+            flow.force_at(Vec2::new(500.0, 0.0), None),
+            Vec2::new(0.0, 1.0)
+        );
+
+        assert_eq!(
+            field_force(&[attractor, repeller, flow], point, None),
+            Vec2::new(0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn field_force_wraps_on_torus() {
+        let torus = Torus::new(100.0, 100.0);
+        let attractor = ForceField::attractor(Vec2::new(95.0, 50.0), 1.0, 20.0);
+        let force = attractor.force_at(Vec2::new(5.0, 50.0), Some(&torus));
+        assert_eq!(force, Vec2::new(-0.5, 0.0));
+    }
+}
@@ -14,3 +14,135 @@ pub fn polygon(settings: &Settings, config: PolygonConfig) -> Vec<Vec2> {
 
     points
 }
+
+/// The point on the segment from `a` to `b` that is closest to `p`.
+pub fn closest_point_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let length_2 = ab.dot(&ab);
+    if length_2 == 0.0 {
+        return a;
+    }
+
+    let t = ((p - a).dot(&ab) / length_2).clamp(0.0, 1.0);
+    a + ab * t
+}
+
+/// Where the segments `a`-`b` and `c`-`d` cross. Segments that only touch at
+/// an endpoint or overlap along a line don't count.
+pub fn segment_intersection(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> Option<Vec2> {
+    let side_a = (d - c).cross(&(a - c));
+    let side_b = (d - c).cross(&(b - c));
+    let side_c = (b - a).cross(&(c - a));
+    let side_d = (b - a).cross(&(d - a));
+
+    let opposite = |s: f64, t: f64| (s > 0.0 && t < 0.0) || (s < 0.0 && t > 0.0);
+    if opposite(side_a, side_b) && opposite(side_c, side_d) {
+        Some(a + (b - a) * (side_a / (side_a - side_b)))
+    } else {
+        None
+    }
+}
+
+/// Whether `point` lies inside the closed polygon `vertices`, by the even-odd
+/// rule. Works for concave and self-intersecting polygons.
+pub fn point_in_polygon(point: Vec2, vertices: &[Vec2]) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len().wrapping_sub(1);
+    for (i, a) in vertices.iter().enumerate() {
+        let b = vertices[j];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// How sharply a polyline turns at `b` on its way from `a` to `c`, in
+/// radians from 0 for straight on to pi for doubling back.
+pub fn turn_angle(a: Vec2, b: Vec2, c: Vec2) -> f64 {
+    let incoming = b - a;
+    let outgoing = c - b;
+    incoming
+        .cross(&outgoing)
+        .atan2(incoming.dot(&outgoing))
+        .abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::*;
+
+    #[test]
+    fn geometry_turn_angle() {
+        let a = Vec2::new(0.0, 0.0);
+        let b = Vec2::new(1.0, 0.0);
+        assert_eq!(turn_angle(a, b, Vec2::new(2.0, 0.0)), 0.0);
+        assert_eq!(
+            turn_angle(a, b, Vec2::new(1.0, -1.0)),
+            std::f64::consts::FRAC_PI_2
+        );
+    }
+
+    #[test]
+    fn geometry_closest_point_on_segment() {
+        let a = Vec2::new(0.0, 0.0);
+        let b = Vec2::new(10.0, 0.0);
+        assert_eq!(
+            closest_point_on_segment(Vec2::new(4.0, 3.0), a, b),
+            Vec2::new(4.0, 0.0)
+        );
+        assert_eq!(closest_point_on_segment(Vec2::new(-4.0, 3.0), a, b), a);
+        assert_eq!(closest_point_on_segment(Vec2::new(1.0, 1.0), a, a), a);
+    }
+
+    #[test]
+    fn geometry_segment_intersection() {
+        let crossing = segment_intersection(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 4.0),
+            Vec2::new(0.0, 4.0),
+            Vec2::new(4.0, 0.0),
+        );
+        assert_eq!(crossing, Some(Vec2::new(2.0, 2.0)));
+
+        // Touching at a shared endpoint is not a crossing.
+        let touching = segment_intersection(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 4.0),
+        );
+        assert_eq!(touching, None);
+
+        let apart = segment_intersection(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, -1.0),
+            Vec2::new(2.0, 1.0),
+        );
+        assert_eq!(apart, None);
+    }
+
+    #[test]
+    fn geometry_point_in_polygon() {
+        // A U shape opening upwards.
+        let u = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(3.0, 0.0),
+            Vec2::new(3.0, 3.0),
+            Vec2::new(2.0, 3.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 3.0),
+            Vec2::new(0.0, 3.0),
+        ];
+        assert!(point_in_polygon(Vec2::new(0.5, 2.0), &u));
+        assert!(point_in_polygon(Vec2::new(1.5, 0.5), &u));
+        assert!(!point_in_polygon(Vec2::new(1.5, 2.0), &u));
+        assert!(!point_in_polygon(Vec2::new(4.0, 1.0), &u));
+        assert!(!point_in_polygon(Vec2::new(0.5, 0.5), &[]));
+    }
+}
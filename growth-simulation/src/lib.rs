@@ -1,13 +1,29 @@
-mod bounds;
+pub mod bounds;
 pub mod config;
+pub mod density;
+#[cfg(feature = "web")]
 mod draw;
-mod geometry;
-mod node;
-mod path;
+pub mod export;
+pub mod field;
+pub mod geometry;
+pub mod node;
+pub mod noise;
+pub mod path;
+pub mod plotter;
+pub mod raster;
+pub mod recording;
 pub mod simulation;
-mod spatial_index;
+pub mod snapshot;
+pub mod spatial_index;
+pub mod style;
+pub mod svg;
+pub mod svg_path;
+pub mod topology;
+pub mod torus;
 mod utils;
-mod vec2;
+pub mod vec2;
+#[cfg(feature = "web")]
+pub mod web;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -1,17 +1,66 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
-use crate::config::Settings;
-use crate::spatial_index::SpatialIndex;
+use crate::bounds::Bounds;
+use crate::config::{Settings, WallMode};
+use crate::geometry::closest_point_on_segment;
+use crate::noise::noise_force;
+use crate::spatial_index::{NodeKey, SpatialIndex};
+use crate::torus::Torus;
 use crate::vec2::Vec2;
 
+/// How many user scalars every node carries. The count is fixed so nodes
+/// stay `Copy`; slots past it are rejected.
+pub const NODE_SCALARS: usize = 4;
+
+/// Names the part of a user scalar's address that doesn't exist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeScalarError {
+    Path(usize),
+    Node(usize),
+    Slot(usize),
+}
+
+impl fmt::Display for NodeScalarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeScalarError::Path(path) => write!(f, "there is no path {}", path),
+            NodeScalarError::Node(node) => write!(f, "there is no node {}", node),
+            NodeScalarError::Slot(slot) => write!(
+                f,
+                "scalar slot {} is out of range (nodes have {})",
+                slot, NODE_SCALARS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NodeScalarError {}
+
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Node {
+    /// Unique within the node's path, stable as nodes are added and removed.
+    #[serde(default)]
+    pub id: u32,
     pub position: Vec2,
     pub velocity: Vec2,
     pub acceleration: Vec2,
     pub fixed: bool,
+    /// The frame the node was created in.
+    #[serde(default)]
+    pub birth: u64,
+    /// How many edge splits it took to produce the node, 0 for the nodes a
+    /// path starts with.
+    #[serde(default)]
+    pub generation: u32,
+    /// Free for rules and rendering to use, `NODE_SCALARS` of them. A node
+    /// that splits an edge starts with the average of the edge's ends.
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub scalars: [f64; NODE_SCALARS],
 }
 
 impl Default for Node {
@@ -29,31 +78,73 @@ enum InteractionType {
 impl Node {
     pub fn new() -> Self {
         Self {
+            id: 0,
             position: Vec2::new(0.0, 0.0),
             velocity: Vec2::new(0.0, 0.0),
             acceleration: Vec2::new(0.0, 0.0),
             fixed: false,
+            birth: 0,
+            generation: 0,
+            scalars: [0.0; NODE_SCALARS],
         }
     }
 
     pub fn new_with_position(position: Vec2) -> Self {
         Self {
+            id: 0,
             position,
             velocity: Vec2::new(0.0, 0.0),
             acceleration: Vec2::new(0.0, 0.0),
             fixed: false,
+            birth: 0,
+            generation: 0,
+            scalars: [0.0; NODE_SCALARS],
         }
     }
 
     pub fn new_with_position_and_velocity(position: Vec2, velocity: Vec2) -> Self {
         Self {
+            id: 0,
             position,
             velocity,
             acceleration: Vec2::new(0.0, 0.0),
             fixed: false,
+            birth: 0,
+            generation: 0,
+            scalars: [0.0; NODE_SCALARS],
         }
     }
 
+    /// A node halfway along the edge from `a` to `b`, created in `frame`.
+    pub fn split(a: &Node, b: &Node, frame: u64) -> Self {
+        Self {
+            birth: frame,
+            generation: a.generation.max(b.generation) + 1,
+            scalars: average_scalars(a, b),
+            ..Self::new_with_position((a.position + b.position) / 2.0)
+        }
+    }
+
+    /// `a` and `b` collapsed into one node at `position`, which keeps the
+    /// identity of `a`. It moves with their average velocity, is fixed if
+    /// either was, and counts as old as the older of the two.
+    pub fn merge(a: &Node, b: &Node, position: Vec2) -> Self {
+        Self {
+            position,
+            velocity: (a.velocity + b.velocity) / 2.0,
+            fixed: a.fixed || b.fixed,
+            birth: a.birth.min(b.birth),
+            generation: a.generation.max(b.generation),
+            scalars: average_scalars(a, b),
+            ..*a
+        }
+    }
+
+    /// Frames since the node was created.
+    pub fn age(&self, frame: u64) -> u64 {
+        frame.saturating_sub(self.birth)
+    }
+
     pub fn distance(&self, other: &Node) -> f64 {
         self.position.distance(&other.position)
     }
@@ -62,7 +153,10 @@ impl Node {
         self.acceleration += force;
     }
 
-    pub fn update(&mut self, settings: &Settings) {
+    /// Integrates the forces gathered this frame plus the noise field at
+    /// `time`, the number of frames since setup.
+    pub fn update(&mut self, settings: &Settings, time: f64, torus: Option<&Torus>) {
+        self.add_force(noise_force(settings, self.position, time, torus));
         self.velocity += self.acceleration;
         self.velocity.limit(settings.max_speed);
         self.position += self.velocity;
@@ -73,6 +167,7 @@ impl Node {
         &mut self,
         settings: &Settings,
         index: &dyn SpatialIndex,
+        excluded: &[NodeKey],
         interaction_type: InteractionType,
     ) {
         let mut total_force = Vec2::new(0.0, 0.0);
@@ -83,11 +178,24 @@ impl Node {
             InteractionType::Avoid => settings.separation_distance,
         };
 
-        let others = index.get_neighbors(&self.position, radius);
+        let others = match settings.max_neighbors {
+            0 => index.locate_within_distance(&self.position, radius),
+            // Ask for enough extra that the excluded nodes can't crowd out
+            // the ones that count.
+            k => index.nearest_neighbors(&self.position, k as usize + excluded.len(), radius),
+        };
+        let limit = match settings.max_neighbors {
+            0 => usize::MAX,
+            k => k as usize,
+        };
 
-        for o in others {
-            let other_pos = Vec2::from_point2(o);
-            let distance = self.position.distance(&other_pos);
+        for other in others
+            .into_iter()
+            .filter(|o| !excluded.contains(&o.key))
+            .take(limit)
+        {
+            let other_pos = Vec2::from_point2(&other.position);
+            let distance = other.distance;
 
             if distance > 0.0 && distance < radius {
                 let mut force = match interaction_type {
@@ -101,8 +209,17 @@ impl Node {
             }
         }
 
-        if near_nodes > 0 {
-            total_force /= near_nodes as f64;
+        let weight = match interaction_type {
+            InteractionType::Attract => settings.attraction_weight,
+            InteractionType::Avoid => settings.separation_weight,
+        };
+        self.steer(settings, total_force, near_nodes, weight);
+    }
+
+    /// Turns the summed per-neighbor forces into a weighted steering force.
+    fn steer(&mut self, settings: &Settings, mut total_force: Vec2, count: usize, weight: f64) {
+        if count > 0 {
+            total_force /= count as f64;
         }
 
         if total_force.length() > 0.0 {
@@ -110,22 +227,89 @@ impl Node {
             total_force *= settings.max_speed;
             total_force -= self.velocity;
             total_force.limit(settings.max_force);
-
-            total_force *= match interaction_type {
-                InteractionType::Attract => settings.attraction_weight,
-                InteractionType::Avoid => settings.separation_weight,
-            };
+            total_force *= weight;
 
             self.add_force(total_force);
         }
     }
 
-    pub fn attract(&mut self, settings: &Settings, index: &dyn SpatialIndex) {
-        self.interact(settings, index, InteractionType::Attract);
+    /// Steers towards nodes within the attraction distance, ignoring the
+    /// `excluded` ones.
+    pub fn attract(&mut self, settings: &Settings, index: &dyn SpatialIndex, excluded: &[NodeKey]) {
+        self.interact(settings, index, excluded, InteractionType::Attract);
+    }
+
+    /// Steers away from nodes within the separation distance, ignoring the
+    /// `excluded` ones.
+    pub fn avoid(&mut self, settings: &Settings, index: &dyn SpatialIndex, excluded: &[NodeKey]) {
+        self.interact(settings, index, excluded, InteractionType::Avoid);
+    }
+
+    /// Steers away from the closest points of the given segments, for those
+    /// within the separation distance.
+    pub fn avoid_segments(
+        &mut self,
+        settings: &Settings,
+        segments: impl IntoIterator<Item = (Vec2, Vec2)>,
+    ) {
+        let mut total_force = Vec2::new(0.0, 0.0);
+        let mut near_segments = 0;
+
+        for (a, b) in segments {
+            let closest = closest_point_on_segment(self.position, a, b);
+            let distance = self.position.distance(&closest);
+
+            if distance > 0.0 && distance < settings.separation_distance {
+                let mut force = self.position - closest;
+                force.normalize();
+                force /= distance;
+                total_force += force;
+                near_segments += 1;
+            }
+        }
+
+        self.steer(
+            settings,
+            total_force,
+            near_segments,
+            settings.separation_weight,
+        );
+    }
+
+    /// Pushes the node back inside once it comes within `wall_distance` of
+    /// the edge of `bounds`, harder the further it gets.
+    pub fn repel_walls(&mut self, settings: &Settings, bounds: &dyn Bounds) {
+        let distance = bounds.distance(self.position);
+        let reach = settings.wall_distance.max(f64::EPSILON);
+        if !distance.is_finite() || distance < -reach {
+            return;
+        }
+
+        let strength = settings.wall_force * (distance + reach) / reach;
+        self.add_force(bounds.gradient(self.position) * -strength);
     }
 
-    pub fn avoid(&mut self, settings: &Settings, index: &dyn SpatialIndex) {
-        self.interact(settings, index, InteractionType::Avoid);
+    /// Brings a node that has left `bounds` back to the edge, mirroring it
+    /// back inside in `WallMode::Reflect`, and drops or flips the outward
+    /// part of its velocity. Does nothing in the other wall modes.
+    pub fn constrain(&mut self, settings: &Settings, bounds: &dyn Bounds) {
+        let (depth, bounce) = match settings.wall_mode {
+            WallMode::Reflect => (2.0, 2.0),
+            WallMode::Clamp => (1.0, 1.0),
+            WallMode::Freeze | WallMode::Repel => return,
+        };
+
+        let distance = bounds.distance(self.position);
+        if !distance.is_finite() || distance <= 0.0 {
+            return;
+        }
+
+        let normal = bounds.gradient(self.position);
+        self.position -= normal * (distance * depth);
+        let outward = self.velocity.dot(&normal);
+        if outward > 0.0 {
+            self.velocity -= normal * (outward * bounce);
+        }
     }
 
     pub fn align(&mut self, prev: &Node, next: &Node, settings: &Settings) {
@@ -140,9 +324,18 @@ impl Node {
     }
 }
 
+fn average_scalars(a: &Node, b: &Node) -> [f64; NODE_SCALARS] {
+    let mut scalars = [0.0; NODE_SCALARS];
+    for (i, scalar) in scalars.iter_mut().enumerate() {
+        *scalar = (a.scalars[i] + b.scalars[i]) / 2.0;
+    }
+    scalars
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::config::Settings;
+    use crate::bounds::CircleBounds;
+    use crate::config::{Settings, WallMode};
     use crate::node::Node;
     use crate::spatial_index::*;
     use crate::vec2::{Point2, Vec2};
@@ -177,6 +370,34 @@ mod tests {
         assert_eq!(node.velocity.y, velocity.y);
     }
 
+    #[test]
+    fn node_split_and_merge() {
+        let mut a = Node::new_with_position_and_velocity(Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0));
+        a.id = 7;
+        a.birth = 3;
+        a.generation = 2;
+        a.scalars[0] = 1.0;
+        let mut b = Node::new_with_position(Vec2::new(2.0, 4.0));
+        b.birth = 5;
+        b.fixed = true;
+        b.scalars[0] = 3.0;
+
+        let split = Node::split(&a, &b, 9);
+        assert_eq!(split.position, Vec2::new(1.0, 2.0));
+        assert_eq!(split.velocity, Vec2::new(0.0, 0.0));
+        assert_eq!((split.birth, split.generation), (9, 3));
+        assert_eq!(split.scalars[0], 2.0);
+        assert!(!split.fixed);
+        assert_eq!(split.age(12), 3);
+
+        let merged = Node::merge(&a, &b, Vec2::new(2.0, 4.0));
+        assert_eq!(merged.id, 7);
+        assert_eq!(merged.velocity, Vec2::new(0.5, 0.0));
+        assert_eq!((merged.birth, merged.generation), (3, 2));
+        assert_eq!(merged.scalars[0], 2.0);
+        assert!(merged.fixed);
+    }
+
     #[test]
     fn node_distance() {
         let node1 = Node::new_with_position(Vec2::new(1.0, 2.0));
@@ -201,7 +422,7 @@ mod tests {
         node.add_force(Vec2::new(1.0, 2.0));
         node.add_force(Vec2::new(2.0, -1.0));
         let settings = Settings::new(100, 100);
-        node.update(&settings);
+        node.update(&settings, 0.0, None);
         assert_eq!(node.position.x, -1.0);
         assert_eq!(node.position.y, 2.0);
         assert_eq!(node.velocity.x, 1.0);
@@ -226,10 +447,11 @@ mod tests {
     fn node_attract() {
         let mut node = Node::new_with_position(Vec2::new(0.0, 0.0));
         let points: Vec<Point2> = vec![[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
-        let settings = Settings::new(100, 100);
+        let mut settings = Settings::new(100, 100);
+        settings.attraction_weight = 1.0;
         let mut index: Box<dyn SpatialIndex> = Box::new(NoIndex::new());
-        index.index(points);
-        node.attract(&settings, index.as_ref());
+        index.index(point_entries(points));
+        node.attract(&settings, index.as_ref(), &[]);
         // @todo update
         assert_eq!(node.acceleration.x, 0.42426406871192857);
         assert_eq!(node.acceleration.y, 0.42426406871192857);
@@ -241,10 +463,97 @@ mod tests {
         let points: Vec<Point2> = vec![[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
         let settings = Settings::new(100, 100);
         let mut index: Box<dyn SpatialIndex> = Box::new(NoIndex::new());
-        index.index(points);
-        node.avoid(&settings, index.as_ref());
+        index.index(point_entries(points));
+        node.avoid(&settings, index.as_ref(), &[]);
         // @todo update
         assert_eq!(node.acceleration.x, -0.42850670939904784);
         assert_eq!(node.acceleration.y, -0.42850670939904784);
     }
+
+    #[test]
+    fn node_avoid_max_neighbors() {
+        let mut node = Node::new_with_position(Vec2::new(0.0, 0.0));
+        let points: Vec<Point2> = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 2.0], [-3.0, 0.0]];
+        let mut settings = Settings::new(100, 100);
+        settings.max_neighbors = 1;
+        let mut index: Box<dyn SpatialIndex> = Box::new(NoIndex::new());
+        index.index(point_entries(points));
+        let own_key = NodeKey { path: 0, node: 0 };
+        node.avoid(&settings, index.as_ref(), &[own_key]);
+        assert_eq!(
+            node.acceleration.x,
+            -settings.max_force * settings.separation_weight
+        );
+        assert_eq!(node.acceleration.y, 0.0);
+    }
+
+    #[test]
+    fn node_avoid_excluded() {
+        let mut node = Node::new_with_position(Vec2::new(0.0, 0.0));
+        let points: Vec<Point2> = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 2.0]];
+        let settings = Settings::new(100, 100);
+        let mut index: Box<dyn SpatialIndex> = Box::new(NoIndex::new());
+        index.index(point_entries(points));
+        let excluded = [NodeKey { path: 0, node: 0 }, NodeKey { path: 0, node: 1 }];
+        node.avoid(&settings, index.as_ref(), &excluded);
+        assert_eq!(node.acceleration.x, 0.0);
+        assert_eq!(
+            node.acceleration.y,
+            -settings.max_force * settings.separation_weight
+        );
+    }
+
+    #[test]
+    fn node_avoid_segments() {
+        let mut node = Node::new_with_position(Vec2::new(5.0, 1.0));
+        let settings = Settings::new(100, 100);
+        let segments = [(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0))];
+        node.avoid_segments(&settings, segments);
+        assert_eq!(node.acceleration.x, 0.0);
+        assert_eq!(
+            node.acceleration.y,
+            settings.max_force * settings.separation_weight
+        );
+    }
+
+    #[test]
+    fn node_repel_walls() {
+        let bounds = CircleBounds::new(0.0, 0.0, 10.0, &Settings::new(0, 0));
+        let mut settings = Settings::new(100, 100);
+        settings.wall_force = 2.0;
+        settings.wall_distance = 4.0;
+
+        let mut far = Node::new_with_position(Vec2::new(2.0, 0.0));
+        far.repel_walls(&settings, &bounds);
+        assert_eq!(far.acceleration, Vec2::new(0.0, 0.0));
+
+        let mut near = Node::new_with_position(Vec2::new(8.0, 0.0));
+        near.repel_walls(&settings, &bounds);
+        assert_eq!(near.acceleration, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn node_constrain() {
+        let bounds = CircleBounds::new(0.0, 0.0, 10.0, &Settings::new(0, 0));
+        let mut settings = Settings::new(100, 100);
+        let outside =
+            Node::new_with_position_and_velocity(Vec2::new(0.0, 12.0), Vec2::new(1.0, 2.0));
+
+        settings.wall_mode = WallMode::Clamp;
+        let mut clamped = outside;
+        clamped.constrain(&settings, &bounds);
+        assert_eq!(clamped.position, Vec2::new(0.0, 10.0));
+        assert_eq!(clamped.velocity, Vec2::new(1.0, 0.0));
+
+        settings.wall_mode = WallMode::Reflect;
+        let mut reflected = outside;
+        reflected.constrain(&settings, &bounds);
+        assert_eq!(reflected.position, Vec2::new(0.0, 8.0));
+        assert_eq!(reflected.velocity, Vec2::new(1.0, -2.0));
+
+        settings.wall_mode = WallMode::Freeze;
+        let mut frozen = outside;
+        frozen.constrain(&settings, &bounds);
+        assert_eq!(frozen.position, outside.position);
+    }
 }
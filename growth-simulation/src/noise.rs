@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::config::Settings;
+use crate::torus::Torus;
+use crate::vec2::Vec2;
+
+/// How the noise force is derived from the underlying Perlin noise.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum NoiseType {
+    /// The curl of the noise, which swirls without bunching nodes together.
+    #[default]
+    Curl,
+    /// The noise value read as an angle, pushing at full strength everywhere.
+    Perlin,
+}
+
+/// Step for the finite differences of the curl, in noise coordinates.
+const CURL_EPSILON: f64 = 1e-3;
+
+/// Spreads a lattice point and seed over 32 bits.
+fn hash(x: i64, y: i64, z: i64, seed: u32) -> u32 {
+    let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9)
+        ^ (seed as u64).wrapping_mul(0xd6e8_feb8_6659_fd93);
+    h ^= h >> 32;
+    h = h.wrapping_mul(0xd6e8_feb8_6659_fd93);
+    h ^= h >> 32;
+    h as u32
+}
+
+/// Dot product of the offset with one of the 12 edge gradients of a cube.
+fn gradient(hash: u32, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Improved Perlin noise, roughly between -1 and 1 and zero on the integer
+/// lattice. Gradients are hashed from `seed` rather than looked up in a
+/// permutation table, so the noise doesn't repeat.
+pub fn perlin(x: f64, y: f64, z: f64, seed: u32) -> f64 {
+    tiled_perlin(x, y, z, seed, None)
+}
+
+/// Perlin noise that repeats every `period` lattice cells along x and y if
+/// given.
+fn tiled_perlin(x: f64, y: f64, z: f64, seed: u32, period: Option<(i64, i64)>) -> f64 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+    let (ix, iy, iz) = (x0 as i64, y0 as i64, z0 as i64);
+    let lattice = |dx: i64, dy: i64| match period {
+        Some((px, py)) => ((ix + dx).rem_euclid(px), (iy + dy).rem_euclid(py)),
+        None => (ix + dx, iy + dy),
+    };
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let (lx, ly) = lattice(dx, dy);
+        gradient(
+            hash(lx, ly, iz + dz, seed),
+            fx - dx as f64,
+            fy - dy as f64,
+            fz - dz as f64,
+        )
+    };
+
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+    let near = lerp(
+        lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+        lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+        v,
+    );
+    let far = lerp(
+        lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+        lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+        v,
+    );
+    lerp(near, far, w)
+}
+
+/// `octaves` layers of Perlin noise, each at twice the frequency and half
+/// the amplitude of the last, scaled back to the range of a single layer.
+pub fn fractal(x: f64, y: f64, z: f64, octaves: u32, seed: u32) -> f64 {
+    tiled_fractal(x, y, z, octaves, seed, None)
+}
+
+/// Fractal noise that repeats every `period` cells of the first octave.
+/// Each octave doubles its period along with its frequency.
+fn tiled_fractal(
+    x: f64,
+    y: f64,
+    z: f64,
+    octaves: u32,
+    seed: u32,
+    period: Option<(i64, i64)>,
+) -> f64 {
+    let (mut total, mut amplitude, mut frequency, mut range) = (0.0, 1.0, 1.0, 0.0);
+    let mut period = period;
+    for octave in 0..octaves.max(1) {
+        total += amplitude
+            * tiled_perlin(
+                x * frequency,
+                y * frequency,
+                z * frequency,
+                seed.wrapping_add(octave),
+                period,
+            );
+        range += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+        period = period.map(|(px, py)| (px.saturating_mul(2), py.saturating_mul(2)));
+    }
+    total / range
+}
+
+/// The noise force on a node at `position`, `time` frames into the
+/// simulation. Zero unless `noise_strength` is set. Curl noise is only
+/// around `noise_strength` on average, since clamping it would reintroduce
+/// the bunching it avoids.
+///
+/// On a torus the scale is rounded so a whole number of lattice cells fits
+/// across the view, which lets the noise tile seamlessly.
+pub fn noise_force(settings: &Settings, position: Vec2, time: f64, torus: Option<&Torus>) -> Vec2 {
+    if settings.noise_strength == 0.0 {
+        return Vec2::new(0.0, 0.0);
+    }
+
+    let (position, scale, period) = match torus {
+        Some(torus) => {
+            let cells = |size: f64| (size * settings.noise_scale).round().max(1.0);
+            let (px, py) = (cells(torus.width), cells(torus.height));
+            let scale = Vec2::new(px / torus.width, py / torus.height);
+            (torus.wrap(position), scale, Some((px as i64, py as i64)))
+        }
+        None => (
+            position,
+            Vec2::new(settings.noise_scale, settings.noise_scale),
+            None,
+        ),
+    };
+    let x = position.x * scale.x;
+    let y = position.y * scale.y;
+    let z = time * settings.noise_speed;
+    let noise = |x: f64, y: f64| {
+        tiled_fractal(x, y, z, settings.noise_octaves, settings.noise_seed, period)
+    };
+
+    let force = match settings.noise_type {
+        NoiseType::Perlin => {
+            let angle = noise(x, y) * std::f64::consts::PI * 2.0;
+            Vec2::new(angle.cos(), angle.sin())
+        }
+        NoiseType::Curl => {
+            let dx =
+                (noise(x + CURL_EPSILON, y) - noise(x - CURL_EPSILON, y)) / (2.0 * CURL_EPSILON);
+            let dy =
+                (noise(x, y + CURL_EPSILON) - noise(x, y - CURL_EPSILON)) / (2.0 * CURL_EPSILON);
+            Vec2::new(dy, -dx)
+        }
+    };
+    force * settings.noise_strength
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::noise::*;
+
+    #[test]
+    fn noise_perlin() {
+        assert_eq!(perlin(3.0, -2.0, 7.0, 1), 0.0);
+        assert_eq!(perlin(0.3, 0.6, 0.1, 1), perlin(0.3, 0.6, 0.1, 1));
+        assert_ne!(perlin(0.3, 0.6, 0.1, 1), perlin(0.3, 0.6, 0.1, 2));
+
+        for i in 0..200 {
+            let t = i as f64 * 0.37;
+            let value = fractal(t, t * 0.5, t * 0.25, 4, 3);
+            assert!((-1.0..=1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn noise_force_types() {
+        let mut settings = Settings::new(100, 100);
+        let position = Vec2::new(31.0, 47.0);
+        assert_eq!(
+            noise_force(&settings, position, 0.0, None),
+            Vec2::new(0.0, 0.0)
+        );
+
+        settings.noise_strength = 0.5;
+        settings.noise_type = NoiseType::Perlin;
+        let force = noise_force(&settings, position, 0.0, None);
+        assert!((force.length() - 0.5).abs() < 1e-9);
+        assert_ne!(noise_force(&settings, position, 100.0, None), force);
+
+        // The curl field has no divergence, so it doesn't pile nodes up.
+        settings.noise_type = NoiseType::Curl;
+        let h = 0.01;
+        let at = |dx: f64, dy: f64| noise_force(&settings, position + Vec2::new(dx, dy), 0.0, None);
+        let divergence = (at(h, 0.0).x - at(-h, 0.0).x + at(0.0, h).y - at(0.0, -h).y) / (2.0 * h);
+        assert!(divergence.abs() < 1e-3);
+    }
+
+    #[test]
+    fn noise_settings_defaults() {
+        let mut json = serde_json::to_value(Settings::new(100, 100)).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        for key in &["noise_scale", "noise_octaves", "noise_speed"] {
+            fields.remove(*key).unwrap();
+        }
+        fields.insert("noise_strength".to_string(), 0.5.into());
+
+        let settings: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                noise_strength: 0.5,
+                ..Settings::new(100, 100)
+            }
+        );
+    }
+
+    #[test]
+    fn noise_force_tiles_on_torus() {
+        let mut settings = Settings::new(100, 80);
+        settings.noise_strength = 1.0;
+        settings.noise_scale = 0.033;
+        settings.noise_octaves = 3;
+        let torus = Torus::new(100.0, 80.0);
+
+        for noise_type in [NoiseType::Curl, NoiseType::Perlin] {
+            settings.noise_type = noise_type;
+            for &(x, y) in &[(0.0, 0.0), (0.0001, 40.0), (99.9999, 12.5), (31.0, 79.9)] {
+                let at = |x, y| noise_force(&settings, Vec2::new(x, y), 5.0, Some(&torus));
+                let here = at(x, y);
+                assert!((at(x + 100.0, y) - here).length() < 1e-6);
+                assert!((at(x - 100.0, y + 80.0) - here).length() < 1e-6);
+            }
+            // Both sides of the seam see the same field.
+            let left = noise_force(&settings, Vec2::new(0.001, 30.0), 5.0, Some(&torus));
+            let right = noise_force(&settings, Vec2::new(99.999, 30.0), 5.0, Some(&torus));
+            assert!((left - right).length() < 0.05);
+        }
+    }
+}
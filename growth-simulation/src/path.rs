@@ -1,22 +1,68 @@
 use std::vec::Vec;
 
-use js_sys::Math::random;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
 use crate::bounds::*;
-use crate::config::{PolygonConfig, Settings};
-use crate::draw::draw_path;
+use crate::config::{InjectionStrategy, PolygonConfig, Settings, SvgConfig, WallMode};
+use crate::density::Density;
+use crate::field::{field_force, ForceField};
 use crate::geometry::*;
-use crate::node::Node;
+use crate::node::{Node, NodeScalarError};
 use crate::spatial_index::*;
+use crate::svg_path::{svg_shapes, SvgPathError};
+use crate::topology::{first_crossing, Crossing, Edge, Topology};
+use crate::torus::Torus;
 use crate::vec2::{Point2, Vec2};
 
+/// What paths grow in: the other nodes, the bounds, and whatever else
+/// varies across the canvas.
+#[derive(Clone, Copy)]
+pub struct Environment<'a> {
+    pub spatial_index: &'a dyn SpatialIndex,
+    pub bounds: &'a dyn Bounds,
+    pub density: Option<Density<'a>>,
+    pub fields: &'a [ForceField],
+    /// Frames since setup. New nodes are born in it and the noise field
+    /// evolves with it.
+    pub frame: u64,
+    /// Where nodes may be injected, or `None` for anywhere.
+    pub injection_mask: Option<&'a dyn Bounds>,
+}
+
+impl<'a> Environment<'a> {
+    pub fn new(spatial_index: &'a dyn SpatialIndex, bounds: &'a dyn Bounds) -> Self {
+        Self {
+            spatial_index,
+            bounds,
+            density: None,
+            fields: &[],
+            frame: 0,
+            injection_mask: None,
+        }
+    }
+
+    /// `settings` as they apply at `point`.
+    pub fn settings_at(&self, settings: &Settings, point: Vec2) -> Settings {
+        settings_at(self.density.as_ref(), settings, point)
+    }
+}
+
+fn settings_at(density: Option<&Density>, settings: &Settings, point: Vec2) -> Settings {
+    match density {
+        Some(density) => density.settings_at(settings, point),
+        None => *settings,
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Path {
     cyclic: bool,
     nodes: Vec<Node>,
+    #[serde(default)]
+    next_id: u32,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -25,9 +71,55 @@ struct NeighborNodes {
     next_node: Option<Node>,
 }
 
+/// How often a move that would cross an edge is halved before it is dropped.
+const MAX_MOVE_HALVINGS: usize = 4;
+
+/// Keeps straight stretches in the running under
+/// `InjectionStrategy::Curvature`, in radians of turn.
+const MIN_CURVATURE_WEIGHT: f64 = 0.01;
+
+/// Shortens the move of node `key` from `from` to `to` until the segments
+/// from it to its path neighbors at `ends` cross none of `edges`.
+fn uncrossed_position(edges: &[Edge], key: NodeKey, ends: &[Vec2], from: Vec2, to: Vec2) -> Vec2 {
+    let mut position = to;
+    for _ in 0..MAX_MOVE_HALVINGS {
+        let crosses = ends
+            .iter()
+            .any(|end| first_crossing(edges, *end, position, &[key]).is_some());
+        if !crosses {
+            return position;
+        }
+        position = (from + position) / 2.0;
+    }
+    from
+}
+
 impl Path {
     pub fn new(nodes: Vec<Node>, cyclic: bool) -> Self {
-        Self { cyclic, nodes }
+        let mut path = Self {
+            cyclic,
+            nodes,
+            next_id: 0,
+        };
+        path.assign_ids();
+        path
+    }
+
+    /// Numbers the nodes in order, also for paths loaded from snapshots that
+    /// predate node ids.
+    pub(crate) fn assign_ids(&mut self) {
+        for (id, node) in self.nodes.iter_mut().enumerate() {
+            node.id = id as u32;
+        }
+        self.next_id = self.nodes.len() as u32;
+    }
+
+    /// A new node halfway along the edge from `prev` to `node`.
+    fn split_edge(&mut self, prev: &Node, node: &Node, frame: u64) -> Node {
+        let mut new_node = Node::split(prev, node, frame);
+        new_node.id = self.next_id;
+        self.next_id += 1;
+        new_node
     }
 
     fn get_prev_node(&self, index: usize) -> Option<Node> {
@@ -57,7 +149,30 @@ impl Path {
         }
     }
 
-    fn grow(&mut self, settings: &Settings) -> bool {
+    /// Indices of the node at `index` and up to `k` nodes on either side of
+    /// it along the path, wrapping around on cyclic paths. Each index is
+    /// listed once.
+    fn topological_neighbors(&self, index: usize, k: usize) -> impl Iterator<Item = usize> {
+        let n_nodes = self.nodes.len();
+        let (start, end) = if self.cyclic {
+            // Once `k` reaches halfway round, both sides meet at the node
+            // opposite `index`, which must only be listed once.
+            let k = k.min(n_nodes / 2);
+            let len = (2 * k + 1).min(n_nodes);
+            (index + n_nodes - k, index + n_nodes - k + len - 1)
+        } else {
+            (index.saturating_sub(k), (index + k).min(n_nodes - 1))
+        };
+        (start..=end).map(move |i| i % n_nodes)
+    }
+
+    fn grow(
+        &mut self,
+        settings: &Settings,
+        density: Option<&Density>,
+        frame: u64,
+        changes: &mut IndexChanges,
+    ) -> bool {
         let n_nodes = self.nodes.len();
         let mut new_nodes = false;
 
@@ -65,9 +180,12 @@ impl Path {
             let index = n_nodes - i - 1;
 
             if let Some(prev_node) = self.get_prev_node(index) {
-                if prev_node.distance(&self.nodes[index]) > settings.max_edge_length {
-                    let position = (self.nodes[index].position + prev_node.position) / 2.0;
-                    let new_node = Node::new_with_position(position);
+                let position = (self.nodes[index].position + prev_node.position) / 2.0;
+                let max_edge_length = settings_at(density, settings, position).max_edge_length;
+                if prev_node.distance(&self.nodes[index]) > max_edge_length {
+                    let node = self.nodes[index];
+                    let new_node = self.split_edge(&prev_node, &node, frame);
+                    changes.insert(new_node.id, position);
                     new_nodes = true;
 
                     if index == 0 {
@@ -82,30 +200,142 @@ impl Path {
         new_nodes
     }
 
-    fn prune(&mut self, settings: &Settings) {
-        for index in 0..self.nodes.len() {
-            if index >= self.nodes.len() {
+    /// The fewest nodes pruning leaves: a cyclic path needs three to
+    /// enclose anything and an open one two to have an edge.
+    fn min_node_count(&self) -> usize {
+        if self.cyclic {
+            3
+        } else {
+            2
+        }
+    }
+
+    fn edge_count(&self) -> usize {
+        match self.nodes.len() {
+            0 | 1 => 0,
+            n if self.cyclic => n,
+            n => n - 1,
+        }
+    }
+
+    /// Collapses edges shorter than the minimum edge length into a single
+    /// node, until none are left or the path is down to its minimum node
+    /// count. The merged node sits at the edge's midpoint with the average
+    /// velocity, unless one end is fixed or ends an open path, in which case
+    /// it stays where that end was. Edges between two such nodes are kept,
+    /// and so are edges whose merge would make edges cross a `topology`.
+    fn prune(
+        &mut self,
+        settings: &Settings,
+        density: Option<&Density>,
+        changes: &mut IndexChanges,
+        topology: Option<(&Topology, &dyn SpatialIndex)>,
+    ) {
+        let mut index = 0;
+        while index < self.edge_count() && self.nodes.len() > self.min_node_count() {
+            let next = (index + 1) % self.nodes.len();
+            let (a, b) = (self.nodes[index], self.nodes[next]);
+            let midpoint = (a.position + b.position) / 2.0;
+            let min_edge_length = settings_at(density, settings, midpoint).min_edge_length;
+            if a.distance(&b) >= min_edge_length {
+                index += 1;
                 continue;
             }
 
-            if let Some(prev_node) = self.get_prev_node(index) {
-                if prev_node.distance(&self.nodes[index]) < settings.min_edge_length {
-                    self.nodes.splice(index..index + 1, vec![]);
+            let last = self.nodes.len() - 1;
+            let pinned_a = a.fixed || (!self.cyclic && index == 0);
+            let pinned_b = b.fixed || (!self.cyclic && next == last);
+            let position = match (pinned_a, pinned_b) {
+                (true, true) => {
+                    index += 1;
+                    continue;
                 }
+                (true, false) => a.position,
+                (false, true) => b.position,
+                (false, false) => midpoint,
+            };
+
+            if let Some((topology, spatial_index)) = topology {
+                let prev = self.get_prev_node(index);
+                let after = self.get_next_node(next);
+                let ignored: Vec<NodeKey> = [Some(a), Some(b), prev, after]
+                    .iter()
+                    .flatten()
+                    .map(|n| changes.key(n.id))
+                    .collect();
+                let crosses = [prev, after].iter().flatten().any(|end| {
+                    topology
+                        .find_crossing(spatial_index, end.position, position, &ignored)
+                        .is_some()
+                });
+                if crosses {
+                    index += 1;
+                    continue;
+                }
+            }
+
+            let merged = Node::merge(&a, &b, position);
+            changes.move_node(a.id, a.position, position);
+            changes.remove(b.id, b.position);
+            self.nodes[index] = merged;
+            self.nodes.remove(next);
+            // The merged node is checked again against its new neighbor.
+            if next < index {
+                index -= 1;
             }
         }
     }
 
-    fn inject_random_nodes(&mut self, settings: &Settings) {
-        if random() > settings.injection_probability {
+    fn inject_nodes(
+        &mut self,
+        settings: &Settings,
+        env: &Environment,
+        rng: &mut impl Rng,
+        changes: &mut IndexChanges,
+    ) {
+        for _ in 0..settings.injection_count {
+            self.inject_node(settings, env, rng, changes);
+        }
+    }
+
+    fn inject_node(
+        &mut self,
+        settings: &Settings,
+        env: &Environment,
+        rng: &mut impl Rng,
+        changes: &mut IndexChanges,
+    ) {
+        if self.nodes.len() < 3 {
+            return;
+        }
+
+        let density = env.density.as_ref();
+
+        // Without a density map the probability is the same everywhere, so
+        // the roll comes first and failed tries skip picking a spot.
+        let roll = rng.gen::<f64>();
+        if density.is_none() && roll > settings.injection_probability {
             return;
         }
 
-        let index = (random() * (self.nodes.len() as f64 - 2.0)) as usize + 1;
+        let torus = env.bounds.torus();
+        let mask = env.injection_mask;
+        let index = match self.pick_injection_index(settings, mask, torus, env.frame, rng) {
+            Some(index) => index,
+            None => return,
+        };
+
+        if density.is_some() {
+            let position = self.nodes[index].position;
+            if roll > settings_at(density, settings, position).injection_probability {
+                return;
+            }
+        }
 
         if let Some(prev_node) = self.get_prev_node(index) {
-            let new_node =
-                Node::new_with_position((prev_node.position + self.nodes[index].position) / 2.0);
+            let node = self.nodes[index];
+            let new_node = self.split_edge(&prev_node, &node, env.frame);
+            changes.insert(new_node.id, new_node.position);
 
             if index == 0 {
                 self.nodes.push(new_node);
@@ -115,42 +345,208 @@ impl Path {
         }
     }
 
+    /// Picks the node that an injected node goes in front of, following
+    /// `settings.injection_strategy`. `None` when the mask leaves no edge.
+    /// On a `torus` edges are checked against the mask in the view.
+    fn pick_injection_index(
+        &self,
+        settings: &Settings,
+        mask: Option<&dyn Bounds>,
+        torus: Option<Torus>,
+        frame: u64,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        let candidates = 1..self.nodes.len() - 1;
+        if settings.injection_strategy == InjectionStrategy::Uniform && mask.is_none() {
+            return Some(rng.gen_range(candidates));
+        }
+
+        let weights: Vec<f64> = candidates
+            .clone()
+            .map(|index| self.injection_weight(index, settings, mask, torus, frame))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = rng.gen::<f64>() * total;
+        for (index, weight) in candidates.zip(weights.iter()) {
+            if target < *weight {
+                return Some(index);
+            }
+            target -= weight;
+        }
+        // Rounding can leave the target just past the last weight.
+        weights.iter().rposition(|w| *w > 0.0).map(|i| i + 1)
+    }
+
+    /// How likely the edge ending at the node at `index` is to be split,
+    /// relative to the other edges.
+    fn injection_weight(
+        &self,
+        index: usize,
+        settings: &Settings,
+        mask: Option<&dyn Bounds>,
+        torus: Option<Torus>,
+        frame: u64,
+    ) -> f64 {
+        let prev = &self.nodes[index - 1];
+        let node = &self.nodes[index];
+        if let Some(mask) = mask {
+            let midpoint = match torus {
+                Some(torus) => {
+                    let end = torus.nearest_image(node.position, prev.position);
+                    torus.wrap((prev.position + end) / 2.0)
+                }
+                None => (prev.position + node.position) / 2.0,
+            };
+            if !mask.contains(midpoint) {
+                return 0.0;
+            }
+        }
+
+        match settings.injection_strategy {
+            InjectionStrategy::Uniform => 1.0,
+            InjectionStrategy::Curvature => {
+                let next = &self.nodes[index + 1];
+                turn_angle(prev.position, node.position, next.position) + MIN_CURVATURE_WEIGHT
+            }
+            InjectionStrategy::Tension => prev.distance(node),
+            InjectionStrategy::Age => (prev.age(frame).min(node.age(frame)) + 1) as f64,
+        }
+    }
+
+    /// Steps every node once. When a `topology` is given, nodes are
+    /// separated from nearby edges instead of nodes, and moves that would
+    /// make edges cross are shortened or rejected. The topology is kept up
+    /// to date with the moves.
     pub fn update(
         &mut self,
         settings: &Settings,
-        spatial_index: &dyn SpatialIndex,
-        bounds: &dyn Bounds,
+        env: &Environment,
+        rng: &mut impl Rng,
+        changes: &mut IndexChanges,
+        mut topology: Option<&mut Topology>,
     ) {
+        let mut excluded = vec![];
+
         for index in 0..self.nodes.len() {
             if self.nodes[index].fixed {
                 continue;
             }
 
+            excluded.clear();
+            excluded.extend(
+                self.topological_neighbors(index, settings.excluded_neighbors as usize)
+                    .map(|i| changes.key(self.nodes[i].id)),
+            );
+
             let neighbors = self.get_neighbor_nodes(index);
             let node = &mut self.nodes[index];
+            let previous_position = node.position;
+            let local = env.settings_at(settings, node.position);
 
             if let Some(prev_node) = neighbors.prev_node {
                 if let Some(next_node) = neighbors.next_node {
-                    node.align(&prev_node, &next_node, settings);
+                    node.align(&prev_node, &next_node, &local);
                 }
             }
 
             if settings.attraction_weight > 0.0 {
-                node.attract(settings, spatial_index);
+                node.attract(&local, env.spatial_index, &excluded);
+            }
+
+            // Covers the edges in separation range as well as any edge that
+            // the node's own edges could cross after this move.
+            let nearby = topology.as_deref().map(|topology| {
+                let reach = topology.longest_edge() + 2.0 * settings.max_speed;
+                topology.edges_near(
+                    env.spatial_index,
+                    &node.position,
+                    local.separation_distance.max(reach),
+                )
+            });
+
+            match &nearby {
+                Some(edges) => node.avoid_segments(
+                    &local,
+                    edges
+                        .iter()
+                        .filter(|e| !excluded.iter().any(|key| e.touches(*key)))
+                        .map(|e| (e.a, e.b)),
+                ),
+                None => node.avoid(&local, env.spatial_index, &excluded),
             }
 
-            node.avoid(settings, spatial_index);
+            let torus = env.bounds.torus();
+            if !env.fields.is_empty() {
+                node.add_force(field_force(env.fields, node.position, torus.as_ref()));
+            }
+
+            if settings.wall_mode == WallMode::Repel {
+                node.repel_walls(&local, env.bounds);
+            }
 
-            node.update(settings);
+            node.update(&local, env.frame as f64, torus.as_ref());
+            node.constrain(&local, env.bounds);
+
+            if let (Some(topology), Some(edges)) = (topology.as_deref_mut(), &nearby) {
+                let key = changes.key(node.id);
+                let ends: Vec<Vec2> = [neighbors.prev_node, neighbors.next_node]
+                    .iter()
+                    .flatten()
+                    .map(|n| n.position)
+                    .collect();
+                node.position =
+                    uncrossed_position(edges, key, &ends, previous_position, node.position);
+                topology.set_position(key, node.position);
+            }
+
+            changes.move_node(node.id, previous_position, node.position);
 
-            if !bounds.contains(node.position) {
+            if settings.wall_mode == WallMode::Freeze && !env.bounds.contains(node.position) {
                 node.fixed = true;
             }
         }
 
-        self.grow(settings);
-        self.prune(settings);
-        self.inject_random_nodes(settings);
+        let density = env.density.as_ref();
+        self.grow(settings, density, env.frame, changes);
+        self.prune(
+            settings,
+            density,
+            changes,
+            topology.as_deref().map(|t| (t, env.spatial_index)),
+        );
+        self.inject_nodes(settings, env, rng, changes);
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Sets user scalar `slot` of the node at `index`. Slots go up to
+    /// `NODE_SCALARS`.
+    pub fn set_scalar(
+        &mut self,
+        index: usize,
+        slot: usize,
+        value: f64,
+    ) -> Result<(), NodeScalarError> {
+        let node = self
+            .nodes
+            .get_mut(index)
+            .ok_or(NodeScalarError::Node(index))?;
+        let scalar = node
+            .scalars
+            .get_mut(slot)
+            .ok_or(NodeScalarError::Slot(slot))?;
+        *scalar = value;
+        Ok(())
+    }
+
+    pub fn is_cyclic(&self) -> bool {
+        self.cyclic
     }
 
     pub fn node_positions(&self) -> Vec<Vec2> {
@@ -161,18 +557,36 @@ impl Path {
         self.nodes.iter().map(|n| n.position.as_point2()).collect()
     }
 
+    /// Every pair of this path's edges that cross. Edges are numbered by
+    /// their first node and the path index is always 0.
+    pub fn crossings(&self) -> Vec<Crossing> {
+        let mut index = RTreeIndex::new();
+        index.index(self.index_entries(0));
+        Topology::new(std::slice::from_ref(self), 0.0, None).crossings(&index)
+    }
+
+    /// Index entries for every node, keyed by `path_index` and node id.
+    pub fn index_entries(&self, path_index: usize) -> Vec<IndexEntry> {
+        self.nodes
+            .iter()
+            .map(|n| {
+                let key = NodeKey {
+                    path: path_index,
+                    node: n.id,
+                };
+                IndexEntry::new(key, n.position.as_point2())
+            })
+            .collect()
+    }
+
     pub fn preprocess(&mut self, settings: &Settings) {
         let mut has_grown = true;
+        let mut changes = IndexChanges::new();
         while has_grown {
-            has_grown = self.grow(settings);
+            has_grown = self.grow(settings, None, 0, &mut changes);
         }
     }
 
-    pub fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
-        let points: Vec<Vec2> = self.nodes.iter().map(|n| n.position).collect();
-        draw_path(ctx, &points, self.cyclic, "#ffffff");
-    }
-
     pub fn horizontal(settings: &Settings) -> Self {
         let mut nodes = vec![];
         let y = settings.height as f64 / 2.0;
@@ -208,11 +622,47 @@ impl Path {
             .collect();
         Self::new(nodes, true)
     }
+
+    /// The path folded onto the view of `torus`, as one path per piece
+    /// between the places where it crosses an edge.
+    pub fn wrapped(&self, torus: &Torus) -> Vec<Self> {
+        let points: Vec<Vec2> = self.nodes.iter().map(|n| n.position).collect();
+        torus
+            .split_polyline(&points, self.cyclic)
+            .into_iter()
+            .map(|(points, closed)| {
+                let nodes = points.into_iter().map(Node::new_with_position).collect();
+                Self::new(nodes, closed)
+            })
+            .collect()
+    }
+
+    /// One path per subpath of the SVG path data in `config`. Closed
+    /// subpaths become cyclic paths.
+    pub fn from_svg(settings: &Settings, config: &SvgConfig) -> Result<Vec<Self>, SvgPathError> {
+        let shapes = svg_shapes(config, settings)?;
+        Ok(shapes
+            .into_iter()
+            .map(|shape| {
+                let cyclic = shape.closed && shape.points.len() > 2;
+                let nodes = shape
+                    .points
+                    .into_iter()
+                    .map(Node::new_with_position)
+                    .collect();
+                Self::new(nodes, cyclic)
+            })
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+    use rand_pcg::Pcg64;
+
     use crate::path::*;
+    use crate::torus::TorusIndex;
 
     #[test]
     fn path_get_prev_node() {
@@ -226,13 +676,28 @@ mod tests {
         let cyclic_path = Path::new(nodes.clone(), true);
 
         assert_eq!(basic_path.get_prev_node(0), None);
-        assert_eq!(cyclic_path.get_prev_node(0), Some(nodes[2]));
-
-        assert_eq!(basic_path.get_prev_node(1), Some(nodes[0]));
-        assert_eq!(cyclic_path.get_prev_node(1), Some(nodes[0]));
-
-        assert_eq!(basic_path.get_prev_node(2), Some(nodes[1]));
-        assert_eq!(cyclic_path.get_prev_node(2), Some(nodes[1]));
+        assert_eq!(
+            cyclic_path.get_prev_node(0).map(|n| n.position),
+            Some(nodes[2].position)
+        );
+
+        assert_eq!(
+            basic_path.get_prev_node(1).map(|n| n.position),
+            Some(nodes[0].position)
+        );
+        assert_eq!(
+            cyclic_path.get_prev_node(1).map(|n| n.position),
+            Some(nodes[0].position)
+        );
+
+        assert_eq!(
+            basic_path.get_prev_node(2).map(|n| n.position),
+            Some(nodes[1].position)
+        );
+        assert_eq!(
+            cyclic_path.get_prev_node(2).map(|n| n.position),
+            Some(nodes[1].position)
+        );
     }
 
     #[test]
@@ -246,14 +711,54 @@ mod tests {
         let basic_path = Path::new(nodes.clone(), false);
         let cyclic_path = Path::new(nodes.clone(), true);
 
-        assert_eq!(basic_path.get_next_node(0), Some(nodes[1]));
-        assert_eq!(cyclic_path.get_next_node(0), Some(nodes[1]));
-
-        assert_eq!(basic_path.get_next_node(1), Some(nodes[2]));
-        assert_eq!(cyclic_path.get_next_node(1), Some(nodes[2]));
+        assert_eq!(
+            basic_path.get_next_node(0).map(|n| n.position),
+            Some(nodes[1].position)
+        );
+        assert_eq!(
+            cyclic_path.get_next_node(0).map(|n| n.position),
+            Some(nodes[1].position)
+        );
+
+        assert_eq!(
+            basic_path.get_next_node(1).map(|n| n.position),
+            Some(nodes[2].position)
+        );
+        assert_eq!(
+            cyclic_path.get_next_node(1).map(|n| n.position),
+            Some(nodes[2].position)
+        );
 
         assert_eq!(basic_path.get_next_node(2), None);
-        assert_eq!(cyclic_path.get_next_node(2), Some(nodes[0]));
+        assert_eq!(
+            cyclic_path.get_next_node(2).map(|n| n.position),
+            Some(nodes[0].position)
+        );
+    }
+
+    #[test]
+    fn path_topological_neighbors() {
+        let nodes: Vec<Node> = (0..6)
+            .map(|i| Node::new_with_position(Vec2::new(i as f64, 0.0)))
+            .collect();
+
+        let basic_path = Path::new(nodes.clone(), false);
+        let cyclic_path = Path::new(nodes.clone(), true);
+
+        let collect = |path: &Path, index, k| -> Vec<usize> {
+            path.topological_neighbors(index, k).collect()
+        };
+        assert_eq!(collect(&basic_path, 0, 0), vec![0]);
+        assert_eq!(collect(&basic_path, 0, 2), vec![0, 1, 2]);
+        assert_eq!(collect(&basic_path, 4, 2), vec![2, 3, 4, 5]);
+        assert_eq!(collect(&cyclic_path, 0, 1), vec![5, 0, 1]);
+        assert_eq!(collect(&cyclic_path, 5, 2), vec![3, 4, 5, 0, 1]);
+        assert_eq!(collect(&cyclic_path, 1, 3), vec![4, 5, 0, 1, 2, 3]);
+        assert_eq!(collect(&cyclic_path, 1, 10), vec![4, 5, 0, 1, 2, 3]);
+
+        let odd_path = Path::new(nodes[..5].to_vec(), true);
+        assert_eq!(collect(&odd_path, 0, 2), vec![3, 4, 0, 1, 2]);
+        assert_eq!(collect(&odd_path, 0, 3), vec![3, 4, 0, 1, 2]);
     }
 
     #[test]
@@ -267,8 +772,14 @@ mod tests {
         let path = Path::new(nodes.clone(), false);
         let neighbors = path.get_neighbor_nodes(1);
 
-        assert_eq!(neighbors.prev_node, Some(nodes[0]));
-        assert_eq!(neighbors.next_node, Some(nodes[2]));
+        assert_eq!(
+            neighbors.prev_node.map(|n| n.position),
+            Some(nodes[0].position)
+        );
+        assert_eq!(
+            neighbors.next_node.map(|n| n.position),
+            Some(nodes[2].position)
+        );
     }
 
     #[test]
@@ -285,8 +796,8 @@ mod tests {
         let mut basic_path = Path::new(nodes.clone(), false);
         let mut cyclic_path = Path::new(nodes, true);
 
-        assert_eq!(basic_path.grow(&settings), true);
-        assert_eq!(cyclic_path.grow(&settings), true);
+        assert!(basic_path.grow(&settings, None, 0, &mut IndexChanges::new()));
+        assert!(cyclic_path.grow(&settings, None, 0, &mut IndexChanges::new()));
 
         let basic_points = basic_path.node_positions();
         let cyclic_points = cyclic_path.node_positions();
@@ -322,7 +833,7 @@ mod tests {
 
         let mut path = Path::new(nodes, true);
 
-        assert_eq!(path.grow(&settings), false);
+        assert!(!path.grow(&settings, None, 0, &mut IndexChanges::new()));
 
         assert_eq!(path.node_positions().len(), 3);
     }
@@ -342,21 +853,314 @@ mod tests {
         let mut basic_path = Path::new(nodes.clone(), false);
         let mut cyclic_path = Path::new(nodes, true);
 
-        basic_path.prune(&settings);
-        cyclic_path.prune(&settings);
+        basic_path.prune(&settings, None, &mut IndexChanges::new(), None);
+        cyclic_path.prune(&settings, None, &mut IndexChanges::new(), None);
+
+        // The open path keeps its end where it was.
+        assert_eq!(
+            basic_path.node_positions(),
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, -0.5)
+            ]
+        );
+
+        // The cyclic path merges at the midpoint and stops at three nodes.
+        assert_eq!(
+            cyclic_path.node_positions(),
+            vec![
+                Vec2::new(0.25, 0.25),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, -0.5)
+            ]
+        );
+    }
 
-        let basic_points = basic_path.node_positions();
-        let cyclic_points = cyclic_path.node_positions();
+    #[test]
+    fn path_prune_merges() {
+        let mut settings = Settings::new(100, 100);
+        settings.min_edge_length = 1.0;
 
-        assert_eq!(basic_points.len(), 3);
-        assert_eq!(cyclic_points.len(), 2);
+        // Runs of short edges collapse until every edge is long enough.
+        let nodes = (0..=10)
+            .map(|i| Node::new_with_position(Vec2::new(i as f64 * 0.3, 0.0)))
+            .collect();
+        let mut path = Path::new(nodes, false);
+        path.prune(&settings, None, &mut IndexChanges::new(), None);
+        let positions = path.node_positions();
+        assert!(positions.windows(2).all(|p| p[0].distance(&p[1]) >= 1.0));
+        assert_eq!(positions[0], Vec2::new(0.0, 0.0));
+        assert_eq!(positions[positions.len() - 1], Vec2::new(3.0, 0.0));
+
+        // Fixed nodes stay put and pass their status on, and velocities are
+        // averaged.
+        let mut fixed = Node::new_with_position(Vec2::new(10.0, 0.0));
+        fixed.fixed = true;
+        let nodes = vec![
+            Node::new_with_position(Vec2::new(0.0, 0.0)),
+            Node::new_with_position_and_velocity(Vec2::new(9.5, 0.0), Vec2::new(1.0, 0.0)),
+            fixed,
+            Node::new_with_position(Vec2::new(20.0, 0.0)),
+        ];
+        let mut path = Path::new(nodes, false);
+        path.prune(&settings, None, &mut IndexChanges::new(), None);
+        assert_eq!(path.nodes().len(), 3);
+        assert_eq!(path.nodes()[1].position, Vec2::new(10.0, 0.0));
+        assert_eq!(path.nodes()[1].velocity, Vec2::new(0.5, 0.0));
+        assert!(path.nodes()[1].fixed);
+
+        // A tiny loop never drops below three nodes.
+        let nodes = (0..6)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::PI / 3.0;
+                Node::new_with_position(Vec2::new(angle.cos(), angle.sin()) * 0.01)
+            })
+            .collect();
+        let mut path = Path::new(nodes, true);
+        path.prune(&settings, None, &mut IndexChanges::new(), None);
+        assert_eq!(path.nodes().len(), 3);
+    }
 
-        assert_eq!(basic_points[0], Vec2::new(0.0, 0.0));
-        assert_eq!(cyclic_points[0], Vec2::new(0.5, 0.5));
+    #[test]
+    fn path_prune_keeps_edges_uncrossed() {
+        let settings = Settings::new(100, 100);
+        let points = |points: &[(f64, f64)]| -> Vec<Node> {
+            points
+                .iter()
+                .map(|(x, y)| Node::new_with_position(Vec2::new(*x, *y)))
+                .collect()
+        };
+        // Merging the short edge in the middle would pull both neighboring
+        // edges across the blocker, which sits inside the bend.
+        let bend = points(&[(2.0, 60.0), (4.0, 50.0), (4.5, 50.0), (6.0, 60.0)]);
+        let prune = |blocker_x: f64, torus: Option<Torus>| {
+            let blocker = points(&[(blocker_x, 50.2), (blocker_x + 0.2, 50.2)]);
+            let paths = vec![Path::new(bend.clone(), false), Path::new(blocker, false)];
+            let inner = index_entries(vec![], SpatialIndexType::RTree, &settings);
+            let mut index: Box<dyn SpatialIndex> = match torus {
+                Some(torus) => Box::new(TorusIndex::new(inner, torus)),
+                None => inner,
+            };
+            index.index(
+                paths
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, p)| p.index_entries(i))
+                    .collect(),
+            );
+            let topology = Topology::new(&paths, 0.0, torus);
+
+            let mut path = paths[0].clone();
+            let mut changes = IndexChanges::new();
+            path.prune(
+                &settings,
+                None,
+                &mut changes,
+                Some((&topology, index.as_ref())),
+            );
+            path.nodes().len()
+        };
+
+        assert_eq!(prune(50.0, None), 3);
+        assert_eq!(prune(4.1, None), 4);
+        // On a torus the copy of the blocker one view over is in the way too.
+        assert_eq!(prune(104.1, None), 3);
+        assert_eq!(prune(104.1, Some(Torus::new(100.0, 100.0))), 4);
+    }
+
+    #[test]
+    fn path_injection_strategies() {
+        let points = [
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (12.0, 0.0),
+            (13.0, 0.0),
+            (13.0, 1.0),
+            (13.0, 2.0),
+        ];
+        let nodes = points
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| Node {
+                birth: i as u64,
+                ..Node::new_with_position(Vec2::new(*x, *y))
+            })
+            .collect();
+        let path = Path::new(nodes, false);
+        let mut settings = Settings::new(100, 100);
+
+        let picks_on =
+            |path: &Path, settings: &Settings, mask: Option<&dyn Bounds>, torus: Option<Torus>| {
+                let mut rng = Pcg64::seed_from_u64(3);
+                let mut counts = [0; 7];
+                for _ in 0..500 {
+                    if let Some(index) =
+                        path.pick_injection_index(settings, mask, torus, 10, &mut rng)
+                    {
+                        counts[index] += 1;
+                    }
+                }
+                counts
+            };
+        let picks =
+            |settings: &Settings, mask: Option<&dyn Bounds>| picks_on(&path, settings, mask, None);
+        let most_picked = |counts: [usize; 7]| (0..7).max_by_key(|i| counts[*i]).unwrap();
+
+        settings.injection_strategy = InjectionStrategy::Tension;
+        assert_eq!(most_picked(picks(&settings, None)), 3);
+
+        settings.injection_strategy = InjectionStrategy::Curvature;
+        assert_eq!(most_picked(picks(&settings, None)), 4);
+        assert_eq!(picks(&settings, None), picks(&settings, None));
+
+        settings.injection_strategy = InjectionStrategy::Age;
+        assert_eq!(most_picked(picks(&settings, None)), 1);
+
+        settings.injection_strategy = InjectionStrategy::Uniform;
+        let corner = vec![
+            Vec2::new(10.0, -1.0),
+            Vec2::new(14.0, -1.0),
+            Vec2::new(14.0, 3.0),
+            Vec2::new(10.0, 3.0),
+        ];
+        let mask = PolygonBounds::new(corner, vec![]);
+        let counts = picks(&settings, Some(&mask));
+        assert_eq!(counts.iter().sum::<usize>(), counts[4] + counts[5]);
+
+        // On a torus nodes a view over are masked like their copies in it.
+        let shifted = Path::new(
+            path.nodes()
+                .iter()
+                .map(|node| Node::new_with_position(node.position + Vec2::new(100.0, 0.0)))
+                .collect(),
+            false,
+        );
+        let torus = Some(Torus::new(100.0, 100.0));
+        assert_eq!(picks_on(&shifted, &settings, Some(&mask), None), [0; 7]);
+        assert_eq!(picks_on(&shifted, &settings, Some(&mask), torus), counts);
+
+        let empty = PolygonBounds::new(vec![], vec![]);
+        assert_eq!(picks(&settings, Some(&empty)), [0; 7]);
+    }
+
+    fn initial_seeded_path(settings: &Settings) -> Path {
+        let mut path = Path::polygon(settings, PolygonConfig::new());
+        path.preprocess(settings);
+        path
+    }
+
+    fn run_seeded(seed: u64, steps: usize) -> Path {
+        let settings = Settings::new(400, 400);
+        // Nodes outside the bounds freeze, so they have to be inside.
+        let bounds = ViewBounds {
+            width: 400.0,
+            height: 400.0,
+        };
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let mut path = initial_seeded_path(&settings);
+
+        for _ in 0..steps {
+            let index = index_entries(path.index_entries(0), SpatialIndexType::RTree, &settings);
+            path.update(
+                &settings,
+                &Environment::new(index.as_ref(), &bounds),
+                &mut rng,
+                &mut IndexChanges::new(),
+                None,
+            );
+        }
+
+        path
+    }
+
+    #[test]
+    fn path_update_is_deterministic_under_seed() {
+        let a = run_seeded(42, 20);
+        let b = run_seeded(42, 20);
+        let c = run_seeded(7, 20);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let initial = initial_seeded_path(&Settings::new(400, 400));
+        assert!(a.nodes().len() > initial.nodes().len());
+        assert!(a.nodes().iter().all(|n| !n.fixed));
+        let moved = initial
+            .nodes()
+            .iter()
+            .filter(|n| {
+                a.nodes()
+                    .iter()
+                    .any(|m| m.id == n.id && m.position != n.position)
+            })
+            .count();
+        assert_eq!(moved, initial.nodes().len());
+    }
+
+    #[test]
+    fn path_update_rejects_crossing_moves() {
+        let mut settings = Settings::new(100, 100);
+        settings.max_speed = 10.0;
+        settings.alignment_weight = 0.0;
+        settings.separation_weight = 0.0;
+        settings.injection_probability = 0.0;
+
+        let line = Path::new(
+            vec![
+                Node::new_with_position(Vec2::new(0.0, 0.0)),
+                Node::new_with_position(Vec2::new(10.0, 0.0)),
+            ],
+            false,
+        );
+        let run = |edges: bool| {
+            let mut path = Path::new(
+                vec![
+                    Node::new_with_position(Vec2::new(4.0, 2.0)),
+                    Node::new_with_position_and_velocity(Vec2::new(5.0, 1.0), Vec2::new(0.0, -3.0)),
+                    Node::new_with_position(Vec2::new(6.0, 2.0)),
+                ],
+                false,
+            );
+            let paths = vec![path.clone(), line.clone()];
+            let entries = paths
+                .iter()
+                .enumerate()
+                .flat_map(|(i, p)| p.index_entries(i))
+                .collect();
+            let index = index_entries(entries, SpatialIndexType::RTree, &settings);
+            let mut topology = Topology::new(&paths, settings.max_speed, None);
+
+            path.update(
+                &settings,
+                &Environment::new(index.as_ref(), &NoBounds {}),
+                &mut Pcg64::seed_from_u64(0),
+                &mut IndexChanges::new(),
+                if edges { Some(&mut topology) } else { None },
+            );
+            path.nodes()[1].position
+        };
+
+        assert_eq!(run(false), Vec2::new(5.0, -2.0));
+        assert_eq!(run(true), Vec2::new(5.0, 0.25));
+    }
+
+    #[test]
+    fn path_crossings() {
+        let points = [(0.0, 0.0), (4.0, 4.0), (4.0, 0.0), (0.0, 4.0)];
+        let nodes: Vec<Node> = points
+            .iter()
+            .map(|(x, y)| Node::new_with_position(Vec2::new(*x, *y)))
+            .collect();
 
-        assert_eq!(basic_points[1], Vec2::new(1.0, 1.0));
-        assert_eq!(cyclic_points[1], Vec2::new(0.0, -0.5));
+        let crossings = Path::new(nodes.clone(), true).crossings();
+        assert_eq!(crossings.len(), 1);
+        assert_eq!(crossings[0].first.edge, 0);
+        assert_eq!(crossings[0].second.edge, 2);
+        assert_eq!(crossings[0].point, Vec2::new(2.0, 2.0));
 
-        assert_eq!(basic_points[2], Vec2::new(0.0, -0.5));
+        let square = [nodes[0], nodes[2], nodes[1], nodes[3]];
+        assert!(Path::new(square.to_vec(), true).crossings().is_empty());
     }
 }
@@ -0,0 +1,317 @@
+use std::fmt::Write;
+
+use crate::config::PlotterConfig;
+use crate::path::Path;
+use crate::utils::format_coord;
+use crate::vec2::Vec2;
+
+/// HPGL plotter units per millimeter.
+const HPGL_UNITS_PER_MM: f64 = 40.0;
+
+/// Maps canvas pixels onto the printable area of the page. The canvas is
+/// scaled uniformly and centered inside the margins, and the y axis is
+/// flipped so the origin sits at the bottom left like most plotters expect.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageTransform {
+    scale: f64,
+    offset: Vec2,
+    page_height: f64,
+}
+
+impl PageTransform {
+    pub fn new(width: u32, height: u32, config: &PlotterConfig) -> Self {
+        let printable_width = (config.page_width - config.margin * 2.0).max(0.0);
+        let printable_height = (config.page_height - config.margin * 2.0).max(0.0);
+        let scale = (printable_width / width as f64).min(printable_height / height as f64);
+        let offset = Vec2::new(
+            (config.page_width - width as f64 * scale) / 2.0,
+            (config.page_height - height as f64 * scale) / 2.0,
+        );
+
+        Self {
+            scale,
+            offset,
+            page_height: config.page_height,
+        }
+    }
+
+    pub fn apply(&self, point: Vec2) -> Vec2 {
+        let scaled = point * self.scale + self.offset;
+        Vec2::new(scaled.x, self.page_height - scaled.y)
+    }
+}
+
+/// A single pen-down polyline in page coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+impl Stroke {
+    fn start(&self) -> Vec2 {
+        self.points[0]
+    }
+
+    fn end(&self) -> Vec2 {
+        if self.closed {
+            self.points[0]
+        } else {
+            self.points[self.points.len() - 1]
+        }
+    }
+
+    /// The points to visit in order, repeating the first one for closed strokes.
+    pub fn vertices(&self) -> Vec<Vec2> {
+        let mut vertices = self.points.clone();
+        if self.closed {
+            vertices.push(self.points[0]);
+        }
+        vertices
+    }
+}
+
+pub fn strokes(paths: &[Path], transform: &PageTransform) -> Vec<Stroke> {
+    paths
+        .iter()
+        .filter(|p| p.nodes().len() > 1)
+        .map(|p| Stroke {
+            points: p
+                .node_positions()
+                .into_iter()
+                .map(|pos| transform.apply(pos))
+                .collect(),
+            closed: p.is_cyclic(),
+        })
+        .collect()
+}
+
+/// Greedily orders strokes to shorten pen-up travel. Each step picks the
+/// stroke whose entry point is nearest the pen. Open strokes may be reversed
+/// and closed strokes are rotated to start at their nearest vertex.
+pub fn order_strokes(mut strokes: Vec<Stroke>, start: Vec2) -> Vec<Stroke> {
+    let mut ordered = Vec::with_capacity(strokes.len());
+    let mut pen = start;
+
+    while !strokes.is_empty() {
+        let mut best_index = 0;
+        let mut best_vertex = 0;
+        let mut best_distance = f64::INFINITY;
+
+        for (index, stroke) in strokes.iter().enumerate() {
+            let candidates: Vec<usize> = if stroke.closed {
+                (0..stroke.points.len()).collect()
+            } else {
+                vec![0, stroke.points.len() - 1]
+            };
+
+            for vertex in candidates {
+                let distance = pen.distance(&stroke.points[vertex]);
+                if distance < best_distance {
+                    best_index = index;
+                    best_vertex = vertex;
+                    best_distance = distance;
+                }
+            }
+        }
+
+        let mut stroke = strokes.swap_remove(best_index);
+        if stroke.closed {
+            stroke.points.rotate_left(best_vertex);
+        } else if best_vertex != 0 {
+            stroke.points.reverse();
+        }
+
+        pen = stroke.end();
+        ordered.push(stroke);
+    }
+
+    ordered
+}
+
+fn plot_strokes(width: u32, height: u32, paths: &[Path], config: &PlotterConfig) -> Vec<Stroke> {
+    let transform = PageTransform::new(width, height, config);
+    order_strokes(strokes(paths, &transform), Vec2::new(0.0, 0.0))
+}
+
+pub fn write_gcode(width: u32, height: u32, paths: &[Path], config: &PlotterConfig) -> String {
+    let mut gcode = String::new();
+    let pen_up = format!("G0 Z{}", format_coord(config.pen_up_z));
+    let pen_down = format!(
+        "G1 Z{} F{}",
+        format_coord(config.pen_down_z),
+        format_coord(config.feed_rate)
+    );
+
+    writeln!(gcode, "G21").unwrap();
+    writeln!(gcode, "G90").unwrap();
+    writeln!(gcode, "{}", pen_up).unwrap();
+
+    for stroke in plot_strokes(width, height, paths, config) {
+        let start = stroke.start();
+        writeln!(
+            gcode,
+            "G0 X{} Y{}",
+            format_coord(start.x),
+            format_coord(start.y)
+        )
+        .unwrap();
+        writeln!(gcode, "{}", pen_down).unwrap();
+
+        for (index, point) in stroke.vertices().iter().enumerate().skip(1) {
+            write!(
+                gcode,
+                "G1 X{} Y{}",
+                format_coord(point.x),
+                format_coord(point.y)
+            )
+            .unwrap();
+            if index == 1 {
+                write!(gcode, " F{}", format_coord(config.feed_rate)).unwrap();
+            }
+            gcode.push('\n');
+        }
+
+        writeln!(gcode, "{}", pen_up).unwrap();
+    }
+
+    writeln!(gcode, "G0 X0 Y0").unwrap();
+    gcode
+}
+
+fn hpgl_coord(point: Vec2) -> String {
+    format!(
+        "{},{}",
+        (point.x * HPGL_UNITS_PER_MM).round() as i64,
+        (point.y * HPGL_UNITS_PER_MM).round() as i64
+    )
+}
+
+pub fn write_hpgl(width: u32, height: u32, paths: &[Path], config: &PlotterConfig) -> String {
+    let mut hpgl = String::from("IN;SP1;\n");
+
+    // HPGL has no feed rate, but VS sets pen velocity in cm/s.
+    writeln!(hpgl, "VS{};", format_coord(config.feed_rate / 600.0)).unwrap();
+
+    for stroke in plot_strokes(width, height, paths, config) {
+        writeln!(hpgl, "PU{};", hpgl_coord(stroke.start())).unwrap();
+        let coords: Vec<String> = stroke
+            .vertices()
+            .into_iter()
+            .skip(1)
+            .map(hpgl_coord)
+            .collect();
+        writeln!(hpgl, "PD{};", coords.join(",")).unwrap();
+    }
+
+    hpgl.push_str("PU0,0;SP0;\n");
+    hpgl
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::node::Node;
+    use crate::plotter::*;
+
+    fn path(points: &[(f64, f64)], cyclic: bool) -> Path {
+        let nodes = points
+            .iter()
+            .map(|(x, y)| Node::new_with_position(Vec2::new(*x, *y)))
+            .collect();
+        Path::new(nodes, cyclic)
+    }
+
+    fn page(width: f64, height: f64) -> PlotterConfig {
+        PlotterConfig {
+            page_width: width,
+            page_height: height,
+            margin: 0.0,
+            ..PlotterConfig::new()
+        }
+    }
+
+    #[test]
+    fn plotter_page_transform() {
+        let transform = PageTransform::new(200, 100, &page(100.0, 100.0));
+        assert_eq!(transform.apply(Vec2::new(0.0, 0.0)), Vec2::new(0.0, 75.0));
+        assert_eq!(
+            transform.apply(Vec2::new(200.0, 100.0)),
+            Vec2::new(100.0, 25.0)
+        );
+    }
+
+    #[test]
+    fn plotter_page_transform_margin() {
+        let mut config = page(120.0, 120.0);
+        config.margin = 10.0;
+        let transform = PageTransform::new(100, 100, &config);
+        assert_eq!(
+            transform.apply(Vec2::new(0.0, 100.0)),
+            Vec2::new(10.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn plotter_order_strokes() {
+        let far = Stroke {
+            points: vec![Vec2::new(10.0, 0.0), Vec2::new(20.0, 0.0)],
+            closed: false,
+        };
+        let reversed = Stroke {
+            points: vec![Vec2::new(5.0, 0.0), Vec2::new(1.0, 0.0)],
+            closed: false,
+        };
+        let ordered = order_strokes(vec![far.clone(), reversed], Vec2::new(0.0, 0.0));
+
+        assert_eq!(ordered[0].points[0], Vec2::new(1.0, 0.0));
+        assert_eq!(ordered[1], far);
+    }
+
+    #[test]
+    fn plotter_order_strokes_rotates_closed() {
+        let square = Stroke {
+            points: vec![
+                Vec2::new(5.0, 5.0),
+                Vec2::new(6.0, 5.0),
+                Vec2::new(6.0, 6.0),
+                Vec2::new(5.0, 6.0),
+            ],
+            closed: true,
+        };
+        let ordered = order_strokes(vec![square], Vec2::new(10.0, 10.0));
+
+        assert_eq!(ordered[0].points[0], Vec2::new(6.0, 6.0));
+        assert_eq!(ordered[0].vertices().len(), 5);
+    }
+
+    #[test]
+    fn plotter_write_gcode() {
+        let paths = vec![path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], true)];
+        let gcode = write_gcode(10, 10, &paths, &page(10.0, 10.0));
+        let lines: Vec<&str> = gcode.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "G21",
+                "G90",
+                "G0 Z5",
+                "G0 X0 Y10",
+                "G1 Z0 F1500",
+                "G1 X10 Y10 F1500",
+                "G1 X10 Y0",
+                "G1 X0 Y10",
+                "G0 Z5",
+                "G0 X0 Y0",
+            ]
+        );
+    }
+
+    #[test]
+    fn plotter_write_hpgl() {
+        let paths = vec![path(&[(0.0, 0.0), (10.0, 0.0)], false)];
+        let hpgl = write_hpgl(10, 10, &paths, &page(10.0, 10.0));
+
+        assert_eq!(hpgl, "IN;SP1;\nVS2.5;\nPU0,400;\nPD400,400;\nPU0,0;SP0;\n");
+    }
+}
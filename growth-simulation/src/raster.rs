@@ -0,0 +1,236 @@
+use crate::bounds::Bounds;
+use crate::export::*;
+use crate::style::StyledPolyline;
+use crate::vec2::Vec2;
+
+pub type Rgb = [u8; 3];
+
+/// Parses `#rrggbb` colors, falling back to white for anything else.
+pub fn parse_hex_color(color: &str) -> Rgb {
+    let hex = color.trim_start_matches('#');
+    if hex.len() != 6 {
+        return [255, 255, 255];
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(255);
+    [channel(0), channel(2), channel(4)]
+}
+
+/// A minimal RGBA software canvas with antialiased line drawing, so frames can
+/// be rendered without a browser.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Raster {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Raster {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Row-major RGBA bytes.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+
+    pub fn fill(&mut self, color: Rgb) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+    }
+
+    fn blend(&mut self, x: i64, y: i64, color: Rgb, alpha: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || alpha <= 0.0 {
+            return;
+        }
+
+        let alpha = alpha.min(1.0);
+        let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+        for (dst, src) in self.pixels[i..i + 3].iter_mut().zip(color.iter()) {
+            *dst = (*dst as f64 + (*src as f64 - *dst as f64) * alpha).round() as u8;
+        }
+        let dst_alpha = self.pixels[i + 3] as f64 / 255.0;
+        self.pixels[i + 3] = ((dst_alpha + alpha * (1.0 - dst_alpha)) * 255.0).round() as u8;
+    }
+
+    /// Draws a one pixel wide antialiased line (Xiaolin Wu's algorithm).
+    pub fn draw_line(&mut self, from: Vec2, to: Vec2, color: Rgb) {
+        let (mut x0, mut y0, mut x1, mut y1) = (from.x, from.y, to.x, to.y);
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+
+        // Only walk the span that can touch the raster.
+        let limit = if steep { self.height } else { self.width } as f64;
+        let x_start = x0.round().max(-1.0);
+        let x_end = x1.round().min(limit);
+        if x_start > x_end || x_start.is_nan() || x_end.is_nan() {
+            return;
+        }
+        let mut intery = y0 + gradient * (x_start - x0);
+
+        let mut plot = |x: i64, y: i64, alpha: f64| {
+            if steep {
+                self.blend(y, x, color, alpha);
+            } else {
+                self.blend(x, y, color, alpha);
+            }
+        };
+
+        for x in x_start as i64..=x_end as i64 {
+            let y = intery.floor();
+            let frac = intery - y;
+            plot(x, y as i64, 1.0 - frac);
+            plot(x, y as i64 + 1, frac);
+            intery += gradient;
+        }
+    }
+
+    /// Draws a line about `width` pixels wide as parallel one pixel lines
+    /// spread across it.
+    pub fn draw_wide_line(&mut self, from: Vec2, to: Vec2, color: Rgb, width: f64) {
+        let strokes = width.round().max(1.0) as usize;
+        let mut normal = Vec2::new(from.y - to.y, to.x - from.x);
+        normal.normalize();
+        for stroke in 0..strokes {
+            let shift = normal * (stroke as f64 - (strokes - 1) as f64 / 2.0);
+            self.draw_line(from + shift, to + shift, color);
+        }
+    }
+
+    pub fn draw_polyline(&mut self, points: &[Vec2], closed: bool, color: Rgb, width: f64) {
+        for pair in points.windows(2) {
+            self.draw_wide_line(pair[0], pair[1], color, width);
+        }
+
+        if closed && points.len() > 2 {
+            self.draw_wide_line(points[points.len() - 1], points[0], color, width);
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&self.pixels).unwrap();
+        }
+        bytes
+    }
+}
+
+/// Renders styled paths and bounds the same way the canvas renderer does.
+pub fn rasterize(
+    width: u32,
+    height: u32,
+    lines: &[StyledPolyline],
+    bounds: &dyn Bounds,
+    options: ExportOptions,
+) -> Raster {
+    let mut raster = Raster::new(width, height);
+
+    if options.include_background {
+        raster.fill(parse_hex_color(BACKGROUND_COLOR));
+    }
+
+    for line in lines.iter() {
+        raster.draw_polyline(&line.points, line.closed, line.color, line.width);
+    }
+
+    if options.include_bounds {
+        for outline in bounds.outline() {
+            raster.draw_polyline(&outline, true, parse_hex_color(BOUNDS_COLOR), 1.0);
+        }
+    }
+
+    raster
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raster::*;
+
+    #[test]
+    fn raster_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8000"), [255, 128, 0]);
+        assert_eq!(parse_hex_color("000000"), [0, 0, 0]);
+        assert_eq!(parse_hex_color("red"), [255, 255, 255]);
+    }
+
+    #[test]
+    fn raster_draw_line() {
+        let mut raster = Raster::new(10, 10);
+        raster.fill([0, 0, 0]);
+        raster.draw_line(Vec2::new(1.0, 5.0), Vec2::new(8.0, 5.0), [255, 255, 255]);
+
+        assert_eq!(raster.pixel(4, 5), [255, 255, 255, 255]);
+        assert_eq!(raster.pixel(4, 2), [0, 0, 0, 255]);
+        assert_eq!(raster.pixel(9, 5), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn raster_draw_wide_line() {
+        let mut raster = Raster::new(10, 10);
+        raster.fill([0, 0, 0]);
+        raster.draw_wide_line(
+            Vec2::new(1.0, 5.0),
+            Vec2::new(8.0, 5.0),
+            [255, 255, 255],
+            3.0,
+        );
+
+        for y in 4..=6 {
+            assert_eq!(raster.pixel(4, y), [255, 255, 255, 255]);
+        }
+        assert_eq!(raster.pixel(4, 2), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn raster_draw_line_out_of_range() {
+        let mut raster = Raster::new(4, 4);
+        raster.draw_line(Vec2::new(-10.0, -3.0), Vec2::new(20.0, 9.0), [255, 0, 0]);
+        assert_eq!(raster.pixels().len(), 64);
+    }
+
+    #[test]
+    fn raster_to_png() {
+        let raster = Raster::new(3, 2);
+        let png = raster.to_png();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
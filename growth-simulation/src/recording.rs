@@ -0,0 +1,211 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::bounds::Bounds;
+use crate::export::ExportOptions;
+use crate::raster::{rasterize, Raster};
+use crate::style::StyledPolyline;
+use crate::vec2::Vec2;
+
+/// Bounds that only remember their outline, so a vector frame can be
+/// rasterized the same way as a live simulation.
+struct Outline(Vec<Vec<Vec2>>);
+
+impl Bounds for Outline {
+    fn outline(&self) -> Vec<Vec<Vec2>> {
+        self.0.clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameContent {
+    Raster(Raster),
+    Vector {
+        lines: Vec<StyledPolyline>,
+        /// Empty unless the bounds were drawn.
+        outline: Vec<Vec<Vec2>>,
+        options: ExportOptions,
+    },
+}
+
+/// One captured frame of a recording.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+    /// The simulation frame it was captured on.
+    pub frame: u64,
+    pub width: u32,
+    pub height: u32,
+    pub content: FrameContent,
+}
+
+impl RecordedFrame {
+    pub fn to_raster(&self) -> Raster {
+        match &self.content {
+            FrameContent::Raster(raster) => raster.clone(),
+            FrameContent::Vector {
+                lines,
+                outline,
+                options,
+            } => rasterize(
+                self.width,
+                self.height,
+                lines,
+                &Outline(outline.clone()),
+                *options,
+            ),
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        self.to_raster().to_png()
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Empty,
+    /// GIF frames are at most 65535 pixels on a side.
+    TooLarge,
+    /// The canvas was resized partway through the recording.
+    SizeChanged,
+    Encode(gif::EncodingError),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::Empty => write!(f, "no frames have been recorded"),
+            RecordingError::TooLarge => write!(f, "frames are too large for a GIF"),
+            RecordingError::SizeChanged => write!(f, "frames differ in size"),
+            RecordingError::Encode(err) => write!(f, "failed to encode GIF: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+/// Frames captured while `RecordingConfig::recording` is on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self { frames: vec![] }
+    }
+
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn push(&mut self, frame: RecordedFrame) {
+        self.frames.push(frame);
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Every frame as a PNG, named `{prefix}00000.png`, `{prefix}00001.png`
+    /// and so on in capture order.
+    pub fn to_png_sequence(&self, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| (format!("{}{:05}.png", prefix, i), frame.to_png()))
+            .collect()
+    }
+
+    /// An endlessly looping GIF showing each frame for `frame_delay`
+    /// milliseconds. Every frame gets its own quantized palette.
+    pub fn to_gif(&self, frame_delay: u32) -> Result<Vec<u8>, RecordingError> {
+        let first = self.frames.first().ok_or(RecordingError::Empty)?;
+        let size = |value: u32| u16::try_from(value).map_err(|_| RecordingError::TooLarge);
+        let (width, height) = (size(first.width)?, size(first.height)?);
+        // GIF delays are in hundredths of a second.
+        let delay = (frame_delay as f64 / 10.0).round().min(u16::MAX as f64) as u16;
+
+        let mut bytes = vec![];
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, width, height, &[])
+                .map_err(RecordingError::Encode)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(RecordingError::Encode)?;
+
+            for frame in self.frames.iter() {
+                if frame.width != first.width || frame.height != first.height {
+                    return Err(RecordingError::SizeChanged);
+                }
+                let raster = frame.to_raster();
+                let mut pixels = raster.pixels().to_vec();
+                let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+                gif_frame.delay = delay;
+                encoder
+                    .write_frame(&gif_frame)
+                    .map_err(RecordingError::Encode)?;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::recording::*;
+
+    fn frame(frame: u64, lines: Vec<StyledPolyline>) -> RecordedFrame {
+        RecordedFrame {
+            frame,
+            width: 8,
+            height: 6,
+            content: FrameContent::Vector {
+                lines,
+                outline: vec![],
+                options: ExportOptions::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn recording_exports() {
+        let mut recording = Recording::new();
+        assert!(matches!(recording.to_gif(40), Err(RecordingError::Empty)));
+
+        let line = StyledPolyline {
+            points: vec![Vec2::new(1.0, 3.0), Vec2::new(7.0, 3.0)],
+            closed: false,
+            color: [255, 0, 0],
+            width: 1.0,
+        };
+        recording.push(frame(0, vec![]));
+        recording.push(frame(5, vec![line]));
+
+        let raster = recording.frames()[1].to_raster();
+        assert_eq!(raster.pixel(4, 3), [255, 0, 0, 255]);
+
+        let sequence = recording.to_png_sequence("frame_");
+        let names: Vec<&str> = sequence.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["frame_00000.png", "frame_00001.png"]);
+        assert_eq!(&sequence[0].1[1..4], b"PNG");
+
+        let gif = recording.to_gif(40).unwrap();
+        assert_eq!(&gif[..6], b"GIF89a");
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+        let mut frames = 0;
+        while let Some(decoded) = decoder.read_next_frame().unwrap() {
+            assert_eq!(decoded.delay, 4);
+            frames += 1;
+        }
+        assert_eq!(frames, 2);
+    }
+}
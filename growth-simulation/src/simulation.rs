@@ -1,180 +1,832 @@
+use std::borrow::Cow;
 use std::vec::Vec;
 
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_test::console_log;
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
 
 use crate::bounds::*;
 use crate::config::*;
-use crate::path::Path;
+use crate::density::{Density, DensityMap};
+use crate::export::ExportOptions;
+use crate::field::ForceField;
+use crate::node::NodeScalarError;
+use crate::path::{Environment, Path};
+use crate::plotter::{write_gcode, write_hpgl};
+use crate::raster::{rasterize, Raster};
+use crate::recording::{FrameContent, RecordedFrame, Recording};
+use crate::snapshot::{Snapshot, SnapshotError};
 use crate::spatial_index;
 use crate::spatial_index::*;
-use crate::utils;
-use crate::vec2::Point2;
+use crate::style::{style_paths, StyledPolyline};
+use crate::svg::write_svg;
+use crate::svg_path::SvgPathError;
+use crate::topology::{Crossing, Topology};
+use crate::torus::TorusIndex;
+
+/// The bounds nodes may be injected in, if the config restricts them.
+fn injection_mask(config: &Config) -> Result<Option<Box<dyn Bounds>>, SvgPathError> {
+    match &config.injection_mask {
+        Some(shape) => shape_bounds(shape, &config.settings).map(Some),
+        None => Ok(None),
+    }
+}
 
-#[wasm_bindgen]
-pub struct GrowthSimulation {
-    ctx: Option<web_sys::CanvasRenderingContext2d>,
+/// The headless simulation core: paths, bounds, spatial index and RNG.
+/// Rendering lives in the `web` feature, so this runs anywhere Rust does.
+pub struct Simulation {
     bounds: Box<dyn Bounds>,
+    /// Built from `config.injection_mask` along with the bounds.
+    injection_mask: Option<Box<dyn Bounds>>,
     config: Config,
     paths: Vec<Path>,
+    rng: Pcg64,
+    /// Frames since setup.
+    frame: u64,
+    index: Box<dyn SpatialIndex>,
+    /// What `index` was built with, or `None` when it must be rebuilt.
+    index_settings: Option<(SpatialIndexType, Settings)>,
+    changes: IndexChanges,
+    /// Kept alongside the index in `SeparationMode::Edges`.
+    topology: Option<Topology>,
+    density_map: Option<DensityMap>,
+    fields: Vec<ForceField>,
+    /// Frames captured while `config.recording` is on. Not part of snapshots.
+    recording: Recording,
 }
 
-#[wasm_bindgen]
-impl GrowthSimulation {
-    pub fn new(width: u32, height: u32) -> Self {
-        console_log!("creating growth simulation");
-        utils::set_panic_hook();
+impl Simulation {
+    pub fn new(config: Config) -> Self {
         Self {
-            ctx: None,
             bounds: Box::new(NoBounds {}),
-            config: Config::new(width, height),
+            injection_mask: None,
+            rng: Pcg64::seed_from_u64(config.seed),
+            config,
+            frame: 0,
             paths: vec![],
+            index: Box::new(NoIndex::new()),
+            index_settings: None,
+            changes: IndexChanges::new(),
+            topology: None,
+            density_map: None,
+            fields: vec![],
+            recording: Recording::new(),
         }
     }
 
-    pub fn set_canvas(&mut self, id: String) {
-        let document = web_sys::window().unwrap().document().unwrap();
-        let app_container = document
-            .query_selector("my-app")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<web_sys::HtmlElement>()
-            .unwrap();
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
 
-        let selector = format!("#{}", id);
-        console_log!("selecting {}", selector);
-        let canvas: web_sys::HtmlCanvasElement = app_container
-            .shadow_root()
-            .unwrap()
-            .query_selector(selector.as_str())
-            .unwrap()
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .map_err(|_| ())
-            .unwrap();
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
 
-        let context = canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<web_sys::CanvasRenderingContext2d>()
-            .unwrap();
+    pub fn bounds(&self) -> &dyn Bounds {
+        self.bounds.as_ref()
+    }
+
+    pub fn paths(&self) -> &[Path] {
+        &self.paths
+    }
 
-        self.ctx = Some(context);
+    pub fn density_map(&self) -> Option<&DensityMap> {
+        self.density_map.as_ref()
+    }
+
+    /// Scales growth locally by the darkness of `density_map`, as set up by
+    /// `config.density`. `None` grows uniformly again.
+    pub fn set_density_map(&mut self, density_map: Option<DensityMap>) {
+        self.density_map = density_map;
+    }
+
+    pub fn fields(&self) -> &[ForceField] {
+        &self.fields
+    }
+
+    /// Adds a force field and returns its index in `fields`.
+    pub fn add_field(&mut self, field: ForceField) -> usize {
+        self.fields.push(field);
+        self.fields.len() - 1
+    }
+
+    /// Removes the field at `index`, shifting later ones down.
+    pub fn remove_field(&mut self, index: usize) -> Option<ForceField> {
+        if index < self.fields.len() {
+            Some(self.fields.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn clear_fields(&mut self) {
+        self.fields.clear();
     }
 
     pub fn add_path(&mut self, path: Path) {
         self.paths.push(path);
+        self.index_settings = None;
     }
 
-    pub fn get_config(&self) -> JsValue {
-        JsValue::from_serde(&self.config).unwrap()
+    /// Starts over from the config. Fails without changing anything if SVG
+    /// path data in the initialization, bounds or injection mask doesn't
+    /// parse.
+    pub fn setup(&mut self) -> Result<(), SvgPathError> {
+        let settings = &self.config.settings;
+        let mut paths = match self.config.initialization.init_type {
+            InitializationType::HorizontalLine => vec![Path::horizontal(settings)],
+            InitializationType::VerticalLine => vec![Path::vertical(settings)],
+            InitializationType::Polygon => vec![Path::polygon(
+                settings,
+                self.config.initialization.polygon_config,
+            )],
+            InitializationType::Svg => {
+                Path::from_svg(settings, &self.config.initialization.svg_config)?
+            }
+        };
+        let bounds = get_bounds(&self.config)?;
+        let injection_mask = injection_mask(&self.config)?;
+
+        for path in paths.iter_mut() {
+            path.preprocess(settings);
+        }
+
+        self.paths = paths;
+        self.bounds = bounds;
+        self.injection_mask = injection_mask;
+        self.rng = Pcg64::seed_from_u64(self.config.seed);
+        self.frame = 0;
+        self.recording.clear();
+        self.index_settings = None;
+        Ok(())
     }
 
-    pub fn update_config(&mut self, val: &JsValue) {
-        console_log!("updating config");
-        let config: Config = val.into_serde().unwrap();
-        self.config = config;
+    pub fn reset(&mut self) {
+        self.paths = vec![];
+        self.index_settings = None;
     }
 
-    pub fn update_settings(&mut self, val: &JsValue) {
-        console_log!("updating settings");
-        let new_settings: Settings = val.into_serde().unwrap();
-        self.config.settings = new_settings;
+    fn all_entries(&self) -> Vec<IndexEntry> {
+        self.paths
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| p.index_entries(i))
+            .collect()
     }
 
-    pub fn update_initialization(&mut self, val: &JsValue) {
-        console_log!("updating initialization");
-        let init: InitializationConfig = val.into_serde().unwrap();
-        self.config.initialization = init;
+    /// Rebuilds the index from scratch if the paths were replaced or the
+    /// index type or settings changed since it was built.
+    fn ensure_index(&mut self) {
+        let index_settings = (self.config.spatial_index, self.config.settings);
+        if self.index_settings == Some(index_settings) {
+            return;
+        }
+
+        let mut index =
+            spatial_index::get_spatial_index(self.config.spatial_index, &self.config.settings);
+        if let Some(torus) = self.bounds.torus() {
+            index = Box::new(TorusIndex::new(index, torus));
+        }
+        index.index(self.all_entries());
+        self.index = index;
+        self.index_settings = Some(index_settings);
+
+        let settings = &self.config.settings;
+        // Nodes move at most once per frame, so that is all the index can lag.
+        self.topology = match settings.separation_mode {
+            SeparationMode::Nodes => None,
+            SeparationMode::Edges => Some(Topology::new(
+                &self.paths,
+                settings.max_speed,
+                self.bounds.torus(),
+            )),
+        };
     }
 
-    pub fn update_recording(&mut self, val: &JsValue) {
-        console_log!("updating recording");
-        let rec: RecordingConfig = val.into_serde().unwrap();
-        self.config.recording = rec;
+    pub fn spatial_index(&mut self) -> &dyn SpatialIndex {
+        self.ensure_index();
+        self.index.as_ref()
     }
 
-    pub fn setup(&mut self) {
-        console_log!("SETUP");
-        self.paths = vec![];
-        match self.config.initialization.init_type {
-            InitializationType::HorizontalLine => {
-                self.paths.push(Path::horizontal(&self.config.settings))
-            }
-            InitializationType::VerticalLine => {
-                self.paths.push(Path::vertical(&self.config.settings))
+    /// Steps every path against the index as it stood at the start of the
+    /// frame, then applies the recorded node edits to it, incrementally
+    /// unless the index would rather be rebuilt. The topology, if any, is
+    /// updated from the same edits.
+    pub fn update(&mut self) {
+        self.ensure_index();
+        self.changes.clear();
+
+        let settings = self.config.settings;
+
+        let env = Environment {
+            spatial_index: self.index.as_ref(),
+            bounds: self.bounds.as_ref(),
+            density: self.density_map.as_ref().map(|map| Density {
+                map,
+                config: self.config.density,
+                torus: self.bounds.torus(),
+            }),
+            fields: &self.fields,
+            frame: self.frame,
+            injection_mask: self.injection_mask.as_deref(),
+        };
+
+        for (i, path) in self.paths.iter_mut().enumerate() {
+            self.changes.set_path(i);
+            let first_change = self.changes.len();
+            path.update(
+                &settings,
+                &env,
+                &mut self.rng,
+                &mut self.changes,
+                self.topology.as_mut(),
+            );
+
+            if let Some(topology) = self.topology.as_mut() {
+                topology.apply(i, path, &self.changes.changes()[first_change..]);
             }
-            InitializationType::Polygon => self.paths.push(Path::polygon(
-                &self.config.settings,
-                self.config.initialization.polygon_config,
-            )),
+        }
+
+        if self.index.prefers_rebuild(self.changes.changes().len()) {
+            self.index.index(self.all_entries());
+        } else {
+            self.index.apply(self.changes.changes());
+        }
+        self.frame += 1;
+        self.capture_frame();
+    }
+
+    /// Records the current frame if `config.recording` asks for it.
+    fn capture_frame(&mut self) {
+        let config = self.config.recording;
+        let full = config.max_frames > 0 && self.recording.len() >= config.max_frames as usize;
+        if !config.recording || full || self.frame % config.interval.max(1) as u64 != 0 {
+            return;
+        }
+
+        let options = ExportOptions {
+            include_bounds: self.config.bounds.draw,
+            include_background: true,
+        };
+        let content = match config.capture_type {
+            CaptureType::Raster => FrameContent::Raster(self.to_raster(options)),
+            CaptureType::Vector => FrameContent::Vector {
+                lines: self.styled_paths(),
+                outline: if options.include_bounds {
+                    self.bounds.outline()
+                } else {
+                    vec![]
+                },
+                options,
+            },
         };
+        self.recording.push(RecordedFrame {
+            frame: self.frame,
+            width: self.config.settings.width,
+            height: self.config.settings.height,
+            content,
+        });
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn clear_recording(&mut self) {
+        self.recording.clear();
+    }
+
+    /// Every pair of crossing edges, within and across paths.
+    pub fn crossings(&mut self) -> Vec<Crossing> {
+        self.ensure_index();
+        Topology::new(&self.paths, 0.0, self.bounds.torus()).crossings(self.index.as_ref())
+    }
+
+    /// Frames since setup, which node ages are measured against.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
 
-        self.bounds = get_bounds(self.config);
+    /// Sets user scalar `slot` of node `node` in path `path`. Nodes have
+    /// `NODE_SCALARS` slots; the error names whichever index is out of range.
+    pub fn set_node_scalar(
+        &mut self,
+        path: usize,
+        node: usize,
+        slot: usize,
+        value: f64,
+    ) -> Result<(), NodeScalarError> {
+        self.paths
+            .get_mut(path)
+            .ok_or(NodeScalarError::Path(path))?
+            .set_scalar(node, slot, value)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.paths.iter().map(|p| p.nodes().len()).sum()
+    }
 
-        for path in self.paths.iter_mut() {
-            path.preprocess(&self.config.settings);
+    /// The paths as they should be drawn: folded onto the view and split
+    /// where they cross an edge when the bounds wrap around.
+    pub fn render_paths(&self) -> Cow<'_, [Path]> {
+        match self.bounds.torus() {
+            Some(torus) => Cow::Owned(self.paths.iter().flat_map(|p| p.wrapped(&torus)).collect()),
+            None => Cow::Borrowed(&self.paths),
         }
     }
 
-    pub fn reset(&mut self) {
-        self.paths = vec![];
+    /// The paths split into runs of one color and width according to the
+    /// style config, folded onto the view like `render_paths`.
+    pub fn styled_paths(&self) -> Vec<StyledPolyline> {
+        let lines = style_paths(&self.paths, &self.config.style, self.frame);
+        match self.bounds.torus() {
+            Some(torus) => lines.iter().flat_map(|line| line.wrapped(&torus)).collect(),
+            None => lines,
+        }
     }
 
-    pub fn draw(&self) {
-        let ctx = self.ctx.as_ref().unwrap();
+    pub fn to_svg(&self, options: ExportOptions) -> String {
+        write_svg(
+            self.config.settings.width,
+            self.config.settings.height,
+            &self.styled_paths(),
+            self.bounds.as_ref(),
+            options,
+        )
+    }
 
-        ctx.save();
+    pub fn to_raster(&self, options: ExportOptions) -> Raster {
+        rasterize(
+            self.config.settings.width,
+            self.config.settings.height,
+            &self.styled_paths(),
+            self.bounds.as_ref(),
+            options,
+        )
+    }
 
-        ctx.clear_rect(
-            0.0,
-            0.0,
-            self.config.settings.width as f64,
-            self.config.settings.height as f64,
-        );
+    pub fn to_png(&self, options: ExportOptions) -> Vec<u8> {
+        self.to_raster(options).to_png()
+    }
 
-        ctx.set_fill_style(&"#000000".into());
+    pub fn to_gcode(&self, plotter_config: &PlotterConfig) -> String {
+        write_gcode(
+            self.config.settings.width,
+            self.config.settings.height,
+            &self.render_paths(),
+            plotter_config,
+        )
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::new(
+            self.config.clone(),
+            self.paths.clone(),
+            self.rng.clone(),
+            self.frame,
+            self.density_map.clone(),
+            self.fields.clone(),
+        )
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) -> Result<(), SnapshotError> {
+        let bounds = get_bounds(&snapshot.config).map_err(SnapshotError::Config)?;
+        let injection_mask = injection_mask(&snapshot.config).map_err(SnapshotError::Config)?;
+        self.config = snapshot.config;
+        self.bounds = bounds;
+        self.injection_mask = injection_mask;
+        self.paths = snapshot.paths;
+        self.rng = snapshot.rng;
+        self.frame = snapshot.frame;
+        self.density_map = snapshot.density_map;
+        self.fields = snapshot.fields;
+        self.index_settings = None;
+        Ok(())
+    }
+
+    /// Serializes the paths, config and RNG state as versioned JSON.
+    pub fn save_state(&self) -> String {
+        self.snapshot().to_json()
+    }
+
+    pub fn load_state(&mut self, json: &str) -> Result<(), SnapshotError> {
+        self.restore(Snapshot::from_json(json)?)
+    }
 
-        ctx.fill_rect(
-            0.0,
-            0.0,
-            self.config.settings.width as f64,
-            self.config.settings.height as f64,
+    /// Compact binary equivalent of `save_state`.
+    pub fn save_state_binary(&self) -> Vec<u8> {
+        self.snapshot().to_binary()
+    }
+
+    pub fn load_state_binary(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        self.restore(Snapshot::from_binary(bytes)?)
+    }
+
+    pub fn to_hpgl(&self, plotter_config: &PlotterConfig) -> String {
+        write_hpgl(
+            self.config.settings.width,
+            self.config.settings.height,
+            &self.render_paths(),
+            plotter_config,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::field::FieldKind;
+    use crate::node::{Node, NODE_SCALARS};
+    use crate::simulation::*;
+    use crate::vec2::Vec2;
+
+    #[test]
+    fn simulation_setup() {
+        let mut simulation = Simulation::new(Config::new(400, 400));
+        simulation.setup().unwrap();
+
+        assert_eq!(simulation.paths().len(), 1);
+        assert!(simulation.node_count() > 50);
+    }
+
+    #[test]
+    fn simulation_update_grows() {
+        let mut simulation = Simulation::new(Config::new(400, 400));
+        simulation.setup().unwrap();
+        let initial_count = simulation.node_count();
+
+        for _ in 0..50 {
+            simulation.update();
+        }
+
+        assert!(simulation.node_count() > initial_count);
+    }
+
+    #[test]
+    fn simulation_to_svg() {
+        let mut config = Config::new(400, 400);
+        config.bounds.bounds_type = BoundsType::Circle;
+        let mut simulation = Simulation::new(config);
+        simulation.setup().unwrap();
+
+        let svg = simulation.to_svg(ExportOptions {
+            include_bounds: true,
+            include_background: true,
+        });
+        assert_eq!(svg.matches("<path").count(), 2);
+        assert_eq!(svg.matches(" Z\"").count(), 2);
+    }
+
+    #[test]
+    fn simulation_svg_initialization_and_bounds() {
+        let mut config = Config::new(400, 400);
+        config.initialization.init_type = InitializationType::Svg;
+        config.initialization.svg_config.path_data =
+            "M0 0 A50 50 0 0 1 100 0 Z M200 0 L300 0".to_string();
+        config.bounds.bounds_type = BoundsType::Svg;
+        config.bounds.svg_config.path_data = "M0 0 H10 V10 H0 Z M3 3 H7 V7 H3 Z".to_string();
+        let mut simulation = Simulation::new(config);
+        simulation.setup().unwrap();
+
+        let cyclic: Vec<bool> = simulation.paths().iter().map(|p| p.is_cyclic()).collect();
+        assert_eq!(cyclic, vec![true, false]);
+        assert!(simulation
+            .bounds()
+            .contains(Vec2::new(200.0, 200.0 - 120.0)));
+        assert!(!simulation.bounds().contains(Vec2::new(200.0, 200.0)));
+
+        simulation.config_mut().initialization.svg_config.path_data = "M0 0 L".to_string();
+        assert!(simulation.setup().is_err());
+        assert_eq!(simulation.paths().len(), 2);
+
+        simulation.config_mut().initialization.svg_config.path_data = "M0 0 L10 0".to_string();
+        simulation.config_mut().bounds.svg_config.path_data = "M0 0 H".to_string();
+        assert!(simulation.setup().is_err());
+        assert_eq!(simulation.paths().len(), 2);
+        assert!(!simulation.bounds().contains(Vec2::new(200.0, 200.0)));
+    }
+
+    #[test]
+    fn simulation_wall_modes_keep_nodes_inside() {
+        for wall_mode in [WallMode::Repel, WallMode::Reflect, WallMode::Clamp] {
+            let mut config = Config::new(400, 400);
+            config.bounds.bounds_type = BoundsType::Circle;
+            config.bounds.circle_config.radius = 120.0;
+            config.settings.wall_mode = wall_mode;
+            let mut simulation = Simulation::new(config);
+            simulation.setup().unwrap();
+
+            for _ in 0..80 {
+                simulation.update();
+            }
+
+            let bounds = simulation.bounds();
+            for node in simulation.paths().iter().flat_map(|p| p.nodes()) {
+                assert!(!node.fixed);
+                assert!(bounds.distance(node.position) < 1e-6, "{:?}", wall_mode);
+            }
+        }
+    }
+
+    #[test]
+    fn simulation_torus_wraps_around() {
+        let mut config = Config::new(100, 100);
+        config.bounds.bounds_type = BoundsType::Torus;
+        config.settings.separation_distance = 8.0;
+        let mut simulation = Simulation::new(config);
+        simulation.setup().unwrap();
+        simulation.reset();
+
+        // A loop straddling the left edge.
+        let nodes = [(-5.0, 40.0), (5.0, 40.0), (5.0, 60.0), (-5.0, 60.0)]
+            .iter()
+            .map(|(x, y)| Node::new_with_position(Vec2::new(*x, *y)))
+            .collect();
+        simulation.add_path(Path::new(nodes, true));
+
+        let across = simulation
+            .spatial_index()
+            .locate_within_distance(&Vec2::new(98.0, 40.0), 5.0);
+        assert_eq!(across.len(), 1);
+        assert_eq!(
+            Vec2::from_point2(&across[0].position),
+            Vec2::new(95.0, 40.0)
         );
 
-        for path in self.paths.iter() {
-            path.draw(ctx);
+        for _ in 0..50 {
+            simulation.update();
         }
 
-        if self.config.bounds.draw {
-            self.bounds.draw(ctx);
+        assert!(simulation.paths()[0].nodes().iter().all(|n| !n.fixed));
+        let pieces = simulation.render_paths();
+        assert!(pieces.len() >= 2);
+        for node in pieces.iter().flat_map(|p| p.nodes()) {
+            assert!((0.0..=100.0).contains(&node.position.x));
+            assert!((0.0..=100.0).contains(&node.position.y));
         }
+    }
+
+    #[test]
+    fn simulation_resume_from_state() {
+        let mut config = Config::new(400, 400);
+        config.bounds.bounds_type = BoundsType::Circle;
+        let mut original = Simulation::new(config);
+        original.setup().unwrap();
+        for _ in 0..20 {
+            original.update();
+        }
+
+        let mut from_json = Simulation::new(Config::new(10, 10));
+        from_json.load_state(&original.save_state()).unwrap();
+        let mut from_binary = Simulation::new(Config::new(10, 10));
+        from_binary
+            .load_state_binary(&original.save_state_binary())
+            .unwrap();
+        assert_eq!(from_json.config(), original.config());
 
-        ctx.restore();
+        for _ in 0..20 {
+            original.update();
+            from_json.update();
+            from_binary.update();
+        }
+
+        assert_eq!(from_json.paths(), original.paths());
+        assert_eq!(from_binary.paths(), original.paths());
     }
 
-    fn get_spatial_index(&self) -> Box<dyn SpatialIndex> {
-        spatial_index::index_points(self.all_points(), SpatialIndexType::RTree)
+    #[test]
+    fn simulation_incremental_index_matches_rebuild() {
+        for index_type in [SpatialIndexType::RTree, SpatialIndexType::Grid] {
+            let mut config = Config::new(400, 400);
+            config.spatial_index = index_type;
+            let mut simulation = Simulation::new(config.clone());
+            simulation.setup().unwrap();
+
+            for _ in 0..30 {
+                simulation.update();
+            }
+
+            let entries = simulation.all_entries();
+            let rebuilt = index_entries(entries.clone(), index_type, &config.settings);
+            let incremental = simulation.spatial_index();
+            assert_eq!(incremental.len(), rebuilt.len());
+
+            for entry in entries.iter() {
+                let position = Vec2::from_point2(&entry.position);
+                assert_eq!(
+                    incremental.get_neighbors(&position, 20.0),
+                    rebuilt.get_neighbors(&position, 20.0)
+                );
+            }
+        }
     }
 
-    pub fn update(&mut self) {
-        /* @todo do this asynchronously between update calls */
-        let index = self.get_spatial_index();
+    #[test]
+    fn simulation_reset() {
+        let mut simulation = Simulation::new(Config::new(400, 400));
+        simulation.setup().unwrap();
+        simulation.reset();
+
+        assert_eq!(simulation.node_count(), 0);
+    }
 
-        for path in self.paths.iter_mut() {
-            path.update(&self.config.settings, index.as_ref(), self.bounds.as_ref());
+    #[test]
+    fn simulation_crossings_across_paths() {
+        let line = |from: Vec2, to: Vec2| {
+            Path::new(
+                vec![Node::new_with_position(from), Node::new_with_position(to)],
+                false,
+            )
+        };
+        let mut simulation = Simulation::new(Config::new(100, 100));
+        simulation.add_path(line(Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)));
+        simulation.add_path(line(Vec2::new(0.0, 10.0), Vec2::new(10.0, 0.0)));
+        simulation.add_path(line(Vec2::new(20.0, 0.0), Vec2::new(20.0, 10.0)));
+
+        let crossings = simulation.crossings();
+        assert_eq!(crossings.len(), 1);
+        assert_eq!((crossings[0].first.path, crossings[0].second.path), (0, 1));
+        assert_eq!(crossings[0].point, Vec2::new(5.0, 5.0));
+    }
+
+    #[test]
+    fn simulation_edge_separation_prevents_crossings() {
+        let mut config = Config::new(400, 400);
+        config.settings.separation_distance = 6.0;
+        config.settings.max_edge_length = 8.0;
+        config.settings.separation_mode = SeparationMode::Edges;
+
+        let mut simulation = Simulation::new(config);
+        simulation.setup().unwrap();
+        for _ in 0..150 {
+            simulation.update();
         }
 
-        self.draw();
+        assert!(simulation.crossings().is_empty());
+    }
+
+    #[test]
+    fn simulation_density_map_scales_growth() {
+        let grow = |darkness: f32| {
+            let mut simulation = Simulation::new(Config::new(400, 400));
+            simulation.set_density_map(Some(DensityMap::new(1, 1, vec![darkness]).unwrap()));
+            simulation.setup().unwrap();
+            for _ in 0..15 {
+                simulation.update();
+            }
+            simulation.node_count()
+        };
+
+        assert!(grow(1.0) > grow(0.0) * 2);
+    }
+
+    #[test]
+    fn simulation_fields_steer_growth() {
+        let centroid = |simulation: &Simulation| {
+            let nodes = simulation.paths()[0].nodes();
+            nodes
+                .iter()
+                .fold(Vec2::new(0.0, 0.0), |sum, n| sum + n.position)
+                / nodes.len() as f64
+        };
+
+        let mut simulation = Simulation::new(Config::new(400, 400));
+        simulation.setup().unwrap();
+        let start = centroid(&simulation);
+
+        let index = simulation.add_field(ForceField::attractor(Vec2::new(400.0, 200.0), 0.5, 0.0));
+        simulation.add_field(ForceField::repeller(Vec2::new(0.0, 0.0), 0.5, 50.0));
+        assert_eq!(
+            simulation.remove_field(1).map(|f| f.kind),
+            Some(FieldKind::Repeller)
+        );
+        assert_eq!(simulation.fields().len(), 1);
+        assert_eq!(index, 0);
+
+        for _ in 0..30 {
+            simulation.update();
+        }
+
+        assert!(centroid(&simulation).x > start.x + 10.0);
+    }
+
+    #[test]
+    fn simulation_noise_varies_growth() {
+        let run = |noise_strength: f64, noise_seed: u32| {
+            let mut config = Config::new(400, 400);
+            config.settings.noise_strength = noise_strength;
+            config.settings.noise_seed = noise_seed;
+            let mut simulation = Simulation::new(config);
+            simulation.setup().unwrap();
+            for _ in 0..20 {
+                simulation.update();
+            }
+            simulation.paths()[0].clone()
+        };
+
+        let plain = run(0.0, 0);
+        assert_ne!(run(0.3, 0), plain);
+        assert_eq!(run(0.3, 0), run(0.3, 0));
+        assert_ne!(run(0.3, 0), run(0.3, 1));
     }
 
-    fn path_points(&self) -> Vec<Vec<Point2>> {
-        self.paths.iter().map(|p| p.node_points()).collect()
+    #[test]
+    fn simulation_injection_mask_and_count() {
+        let grow = |injection_count: u32, injection_mask: Option<BoundsShape>| {
+            let mut config = Config::new(400, 400);
+            config.settings.injection_count = injection_count;
+            config.injection_mask = injection_mask;
+            let mut simulation = Simulation::new(config);
+            simulation.setup().unwrap();
+            for _ in 0..10 {
+                simulation.update();
+            }
+            simulation.node_count()
+        };
+
+        // The starting polygon lies well outside this small square.
+        let center = BoundsShape::Rect(RectConfig {
+            width: 10.0,
+            height: 10.0,
+        });
+        assert!(grow(5, None) > grow(1, None));
+        assert!(grow(5, Some(center)) < grow(1, None));
     }
 
-    fn all_points(&self) -> Vec<Point2> {
-        self.path_points().into_iter().flatten().collect()
+    #[test]
+    fn simulation_node_attributes() {
+        let mut simulation = Simulation::new(Config::new(400, 400));
+        simulation.setup().unwrap();
+        let initial = simulation.paths()[0].nodes().len();
+        for node in 0..initial {
+            simulation.set_node_scalar(0, node, 1, 2.0).unwrap();
+        }
+        assert_eq!(
+            simulation.set_node_scalar(1, 0, 1, 2.0),
+            Err(NodeScalarError::Path(1))
+        );
+        assert_eq!(
+            simulation.set_node_scalar(0, initial, 1, 2.0),
+            Err(NodeScalarError::Node(initial))
+        );
+        let err = simulation.set_node_scalar(0, 0, NODE_SCALARS, 2.0);
+        assert_eq!(err, Err(NodeScalarError::Slot(NODE_SCALARS)));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "scalar slot 4 is out of range (nodes have 4)"
+        );
+
+        for _ in 0..20 {
+            simulation.update();
+        }
+
+        let nodes = simulation.paths()[0].nodes();
+        assert!(nodes.len() > initial);
+        assert!(nodes
+            .iter()
+            .all(|n| n.scalars[1] == 2.0 && n.scalars[0] == 0.0));
+        assert!(nodes.iter().all(|n| n.birth < simulation.frame()));
+        assert!(nodes.iter().any(|n| n.birth > 0 && n.generation > 0));
+    }
+
+    #[test]
+    fn simulation_recording() {
+        let mut config = Config::new(100, 100);
+        config.recording.interval = 3;
+        config.recording.max_frames = 3;
+        let mut simulation = Simulation::new(config);
+        simulation.setup().unwrap();
+        simulation.update();
+        assert!(simulation.recording().is_empty());
+
+        simulation.config_mut().recording.recording = true;
+        for _ in 0..14 {
+            simulation.update();
+        }
+        let frames: Vec<u64> = simulation
+            .recording()
+            .frames()
+            .iter()
+            .map(|f| f.frame)
+            .collect();
+        assert_eq!(frames, vec![3, 6, 9]);
+
+        // Both kinds of capture render the same image.
+        simulation.config_mut().recording.interval = 1;
+        simulation.config_mut().recording.max_frames = 0;
+        simulation.clear_recording();
+        simulation.update();
+        simulation.config_mut().recording.capture_type = CaptureType::Raster;
+        simulation.update();
+        let recorded = simulation.recording().frames();
+        assert_eq!(recorded[0].frame, 16);
+        assert!(matches!(recorded[0].content, FrameContent::Vector { .. }));
+        assert!(matches!(recorded[1].content, FrameContent::Raster(_)));
+        assert_eq!(
+            recorded[1].to_raster(),
+            simulation.to_raster(ExportOptions::new())
+        );
+
+        simulation.setup().unwrap();
+        assert!(simulation.recording().is_empty());
     }
 }
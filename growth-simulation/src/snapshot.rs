@@ -0,0 +1,260 @@
+use std::fmt;
+
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::density::DensityMap;
+use crate::field::ForceField;
+use crate::path::Path;
+use crate::svg_path::SvgPathError;
+
+/// Bumped whenever the serialized layout changes. JSON snapshots of older
+/// versions are migrated on load; binary snapshots must match exactly.
+///
+/// 1. Paths, config and RNG state.
+/// 2. Node ids, birth, generation and scalars, the frame, density map and
+///    force fields, and the config added since.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Everything needed to resume a simulation exactly where it left off,
+/// including the RNG state. Bounds are rebuilt from the config on load.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub config: Config,
+    pub paths: Vec<Path>,
+    pub rng: Pcg64,
+    /// Frames since setup.
+    #[serde(default)]
+    pub frame: u64,
+    #[serde(default)]
+    pub density_map: Option<DensityMap>,
+    #[serde(default)]
+    pub fields: Vec<ForceField>,
+}
+
+/// Read ahead of the body so unsupported versions are rejected before the
+/// rest of the data is interpreted.
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    UnsupportedVersion(u32),
+    /// The config holds SVG path data that doesn't parse.
+    Config(SvgPathError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Json(err) => write!(f, "invalid JSON snapshot: {}", err),
+            SnapshotError::Binary(err) => write!(f, "invalid binary snapshot: {}", err),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported snapshot version {} (expected {})",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Config(err) => write!(f, "invalid snapshot config: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// JSON fills in fields added since `version` with their defaults and
+/// `migrate` does the rest, so older JSON snapshots load too. Binary
+/// snapshots are positional and must match.
+fn check_version(version: u32, accept_older: bool) -> Result<(), SnapshotError> {
+    if version == SNAPSHOT_VERSION || (accept_older && (1..SNAPSHOT_VERSION).contains(&version)) {
+        Ok(())
+    } else {
+        Err(SnapshotError::UnsupportedVersion(version))
+    }
+}
+
+impl Snapshot {
+    pub fn new(
+        config: Config,
+        paths: Vec<Path>,
+        rng: Pcg64,
+        frame: u64,
+        density_map: Option<DensityMap>,
+        fields: Vec<ForceField>,
+    ) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            config,
+            paths,
+            rng,
+            frame,
+            density_map,
+            fields,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let header: SnapshotHeader = serde_json::from_str(json).map_err(SnapshotError::Json)?;
+        check_version(header.version, true)?;
+        let mut snapshot: Snapshot = serde_json::from_str(json).map_err(SnapshotError::Json)?;
+        snapshot.migrate();
+        Ok(snapshot)
+    }
+
+    /// Brings a snapshot of an older version up to this one. Fields added
+    /// since have their serde defaults by now.
+    fn migrate(&mut self) {
+        if self.version < 2 {
+            // Nodes had no ids yet, so every one of them came in as 0.
+            for path in self.paths.iter_mut() {
+                path.assign_ids();
+            }
+        }
+        self.version = SNAPSHOT_VERSION;
+    }
+
+    pub fn to_binary(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let header: SnapshotHeader = bincode::deserialize(bytes).map_err(SnapshotError::Binary)?;
+        check_version(header.version, false)?;
+        bincode::deserialize(bytes).map_err(SnapshotError::Binary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::config::RecordingConfig;
+    use crate::node::Node;
+    use crate::simulation::Simulation;
+    use crate::snapshot::*;
+    use crate::style::StyleConfig;
+    use crate::vec2::Vec2;
+
+    fn snapshot() -> Snapshot {
+        let nodes = vec![
+            Node::new_with_position(Vec2::new(0.0, 0.0)),
+            Node::new_with_position_and_velocity(Vec2::new(1.5, 2.0), Vec2::new(0.1, -0.2)),
+        ];
+        Snapshot::new(
+            Config::new(100, 100),
+            vec![Path::new(nodes, true)],
+            Pcg64::seed_from_u64(9),
+            12,
+            Some(DensityMap::new(2, 1, vec![0.25, 1.0]).unwrap()),
+            vec![ForceField::flow(
+                Vec2::new(50.0, 50.0),
+                Vec2::new(1.0, 0.0),
+                0.5,
+                30.0,
+            )],
+        )
+    }
+
+    #[test]
+    fn snapshot_json_round_trip() {
+        let original = snapshot();
+        let restored = Snapshot::from_json(&original.to_json()).unwrap();
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn snapshot_binary_round_trip() {
+        let original = snapshot();
+        let restored = Snapshot::from_binary(&original.to_binary()).unwrap();
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn snapshot_json_fills_missing_fields() {
+        let original = snapshot();
+        // Cut out fields added after the first snapshots, the last of the
+        // config and the last two of the snapshot. The RNG state is too
+        // wide for `serde_json::Value`, so this works on the text.
+        let json = original.to_json();
+        let style = json.find(r#","style":"#).unwrap();
+        let paths = json.find(r#"},"paths":"#).unwrap();
+        let frame = json.find(r#","frame":"#).unwrap();
+        let json = format!("{}{}{}", &json[..style], &json[paths..frame], "}");
+        let mut json = json;
+        for field in &[
+            r#""injection_count":1,"#,
+            r#""wall_force":1.0,"wall_distance":10.0,"#,
+            r#""interval":1,"#,
+            r#","frame_delay":40"#,
+        ] {
+            assert!(json.contains(field));
+            json = json.replacen(field, "", 1);
+        }
+
+        let restored = Snapshot::from_json(&json).unwrap();
+        assert_eq!(restored.config.settings, original.config.settings);
+        assert_eq!(restored.config.recording, RecordingConfig::new());
+        assert!(restored.fields.is_empty());
+        assert!(restored.density_map.is_none());
+        assert_eq!(restored.frame, 0);
+        assert_eq!(restored.config.style, StyleConfig::new());
+        assert_eq!(restored.paths, original.paths);
+    }
+
+    #[test]
+    fn snapshot_loads_version_1() {
+        let json = include_str!("../tests/fixtures/snapshot_v1.json");
+        let snapshot = Snapshot::from_json(json).unwrap();
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.config.seed, 5);
+        assert_eq!(snapshot.frame, 0);
+
+        let nodes = snapshot.paths[0].nodes();
+        assert_eq!(nodes.len(), 22);
+        let ids: Vec<u32> = nodes.iter().map(|n| n.id).collect();
+        assert_eq!(ids, (0..22).collect::<Vec<u32>>());
+
+        // New nodes get fresh ids once the simulation resumes.
+        let mut simulation = Simulation::new(Config::new(100, 100));
+        simulation.load_state(json).unwrap();
+        for _ in 0..20 {
+            simulation.update();
+        }
+        let mut ids: Vec<u32> = simulation.paths()[0].nodes().iter().map(|n| n.id).collect();
+        assert!(ids.len() > 22);
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), simulation.node_count());
+
+        // The binary layout has changed since, so it can't be read.
+        let bytes = include_bytes!("../tests/fixtures/snapshot_v1.bin");
+        assert!(matches!(
+            Snapshot::from_binary(bytes),
+            Err(SnapshotError::UnsupportedVersion(1))
+        ));
+    }
+
+    #[test]
+    fn snapshot_rejects_unknown_version() {
+        let mut original = snapshot();
+        original.version = SNAPSHOT_VERSION + 1;
+
+        assert!(matches!(
+            Snapshot::from_json(&original.to_json()),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            Snapshot::from_binary(&original.to_binary()),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -1,75 +1,818 @@
+use std::collections::BTreeMap;
+
+use crate::config::Settings;
 use crate::vec2::*;
 
-use rstar::{RTree, AABB};
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Identifies a node across frames: the index of its path in the simulation
+/// and the node's id within that path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeKey {
+    pub path: usize,
+    pub node: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndexEntry {
+    pub key: NodeKey,
+    pub position: Point2,
+}
+
+impl IndexEntry {
+    pub fn new(key: NodeKey, position: Point2) -> Self {
+        Self { key, position }
+    }
+}
+
+impl RTreeObject for IndexEntry {
+    type Envelope = AABB<Point2>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point(self.position)
+    }
+}
+
+impl PointDistance for IndexEntry {
+    fn distance_2(&self, point: &Point2) -> f64 {
+        let dx = self.position[0] - point[0];
+        let dy = self.position[1] - point[1];
+        dx * dx + dy * dy
+    }
+}
+
+/// An indexed node found by a radius or nearest-neighbor query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Neighbor {
+    pub key: NodeKey,
+    pub position: Point2,
+    pub distance: f64,
+}
+
+impl Neighbor {
+    fn new(key: NodeKey, position: Point2, from: &Vec2) -> Self {
+        Self {
+            key,
+            position,
+            distance: from.distance(&Vec2::from_point2(&position)),
+        }
+    }
+}
+
+/// Orders neighbors nearest first, breaking ties by key. A NaN distance,
+/// from a NaN position, sorts to one end instead of panicking.
+fn sort_nearest(neighbors: &mut [Neighbor]) {
+    neighbors.sort_unstable_by(|a, b| a.distance.total_cmp(&b.distance).then(a.key.cmp(&b.key)));
+}
+
+/// A single edit to the set of indexed nodes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexChange {
+    Insert(IndexEntry),
+    Remove(IndexEntry),
+    /// The entry as currently indexed and the position it moved to.
+    Move(IndexEntry, Point2),
+}
+
+/// Collects the node edits made while updating paths so they can be applied
+/// to the index afterwards. The buffer is reused across frames.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexChanges {
+    path: usize,
+    changes: Vec<IndexChange>,
+}
+
+impl IndexChanges {
+    pub fn new() -> Self {
+        Self {
+            path: 0,
+            changes: vec![],
+        }
+    }
+
+    /// Sets the path that subsequent edits belong to.
+    pub fn set_path(&mut self, path: usize) {
+        self.path = path;
+    }
+
+    /// The key of node `node` in the current path.
+    pub fn key(&self, node: u32) -> NodeKey {
+        NodeKey {
+            path: self.path,
+            node,
+        }
+    }
+
+    fn entry(&self, node: u32, position: Vec2) -> IndexEntry {
+        IndexEntry::new(self.key(node), position.as_point2())
+    }
+
+    pub fn insert(&mut self, node: u32, position: Vec2) {
+        let entry = self.entry(node, position);
+        self.changes.push(IndexChange::Insert(entry));
+    }
+
+    pub fn remove(&mut self, node: u32, position: Vec2) {
+        let entry = self.entry(node, position);
+        self.changes.push(IndexChange::Remove(entry));
+    }
+
+    pub fn move_node(&mut self, node: u32, from: Vec2, to: Vec2) {
+        if from != to {
+            let entry = self.entry(node, from);
+            self.changes.push(IndexChange::Move(entry, to.as_point2()));
+        }
+    }
+
+    pub fn changes(&self) -> &[IndexChange] {
+        &self.changes
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
 
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+}
+
+/// Neighbor queries over the simulation's nodes. Results come back in an
+/// order that depends only on the indexed entries, never on the history of
+/// edits, so an incrementally updated index gives bit-identical forces to a
+/// freshly built one.
 pub trait SpatialIndex {
-    fn index(&mut self, points: Vec<Point2>);
+    /// Replaces the contents of the index.
+    fn index(&mut self, entries: Vec<IndexEntry>);
+
+    fn insert(&mut self, entry: IndexEntry);
+
+    /// Removes an entry, which must match what was indexed exactly.
+    fn remove(&mut self, entry: &IndexEntry);
+
+    fn move_entry(&mut self, entry: &IndexEntry, to: Point2) {
+        self.remove(entry);
+        self.insert(IndexEntry::new(entry.key, to));
+    }
+
+    fn apply(&mut self, changes: &[IndexChange]) {
+        for change in changes.iter() {
+            match change {
+                IndexChange::Insert(entry) => self.insert(*entry),
+                IndexChange::Remove(entry) => self.remove(entry),
+                IndexChange::Move(entry, to) => self.move_entry(entry, *to),
+            }
+        }
+    }
+
+    /// Whether indexing everything from scratch is cheaper than applying
+    /// this many changes one by one.
+    fn prefers_rebuild(&self, _changes: usize) -> bool {
+        false
+    }
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
+    /// Everything inside the axis-aligned square of half-width `radius`.
     fn get_neighbors(&self, position: &Vec2, radius: f64) -> Vec<&Point2>;
+
+    /// Everything within `radius` of `position`, inclusive.
+    fn locate_within_distance(&self, position: &Vec2, radius: f64) -> Vec<Neighbor>;
+
+    /// The `k` entries within `radius` that are closest to `position`,
+    /// nearest first.
+    fn nearest_neighbors(&self, position: &Vec2, k: usize, radius: f64) -> Vec<Neighbor> {
+        let mut neighbors = self.locate_within_distance(position, radius);
+        sort_nearest(&mut neighbors);
+        neighbors.truncate(k);
+        neighbors
+    }
 }
 
 pub struct NoIndex {
-    others: Vec<Point2>,
+    others: BTreeMap<NodeKey, Point2>,
 }
 
 impl NoIndex {
     pub fn new() -> Self {
-        Self { others: vec![] }
+        Self {
+            others: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for NoIndex {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl SpatialIndex for NoIndex {
-    fn index(&mut self, points: Vec<Point2>) {
-        self.others = points;
+    fn index(&mut self, entries: Vec<IndexEntry>) {
+        self.others = entries.into_iter().map(|e| (e.key, e.position)).collect();
+    }
+
+    fn insert(&mut self, entry: IndexEntry) {
+        self.others.insert(entry.key, entry.position);
+    }
+
+    fn remove(&mut self, entry: &IndexEntry) {
+        self.others.remove(&entry.key);
+    }
+
+    fn move_entry(&mut self, entry: &IndexEntry, to: Point2) {
+        self.others.insert(entry.key, to);
+    }
+
+    fn len(&self) -> usize {
+        self.others.len()
     }
 
     fn get_neighbors(&self, _position: &Vec2, _radius: f64) -> Vec<&Point2> {
-        self.others.iter().collect()
+        self.others.values().collect()
+    }
+
+    fn locate_within_distance(&self, position: &Vec2, radius: f64) -> Vec<Neighbor> {
+        self.others
+            .iter()
+            .map(|(key, p)| Neighbor::new(*key, *p, position))
+            .filter(|n| n.distance <= radius)
+            .collect()
     }
 }
 
 pub struct RTreeIndex {
-    rtree: Option<RTree<Point2>>,
+    rtree: RTree<IndexEntry>,
 }
 
 impl RTreeIndex {
     pub fn new() -> Self {
-        Self { rtree: None }
+        Self {
+            rtree: RTree::new(),
+        }
+    }
+}
+
+impl Default for RTreeIndex {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl SpatialIndex for RTreeIndex {
-    fn index(&mut self, points: Vec<Point2>) {
-        self.rtree = Some(RTree::bulk_load(points));
+    fn index(&mut self, entries: Vec<IndexEntry>) {
+        self.rtree = RTree::bulk_load(entries);
+    }
+
+    fn insert(&mut self, entry: IndexEntry) {
+        self.rtree.insert(entry);
+    }
+
+    fn remove(&mut self, entry: &IndexEntry) {
+        self.rtree.remove(entry);
+    }
+
+    /// Edits in an R-tree cost a descent each. The `apply` bench puts the
+    /// break-even with a bulk reload at about a tenth of the tree, which a
+    /// growing simulation passes most frames; `GridIndex` updates in place
+    /// instead.
+    fn prefers_rebuild(&self, changes: usize) -> bool {
+        changes >= self.rtree.size() / 10
+    }
+
+    fn len(&self) -> usize {
+        self.rtree.size()
     }
 
     fn get_neighbors(&self, position: &Vec2, radius: f64) -> Vec<&Point2> {
         let bottom_corner = *position - radius;
         let top_corner = *position + radius;
         let radius_square = AABB::from_corners(bottom_corner.as_point2(), top_corner.as_point2());
-        self.rtree
-            .as_ref()
-            .unwrap()
-            .locate_in_envelope(&radius_square)
+        let mut entries: Vec<&IndexEntry> = self.rtree.locate_in_envelope(&radius_square).collect();
+        // The tree layout depends on insertion order, so sort for stability.
+        entries.sort_unstable_by_key(|e| e.key);
+        entries.into_iter().map(|e| &e.position).collect()
+    }
+
+    fn locate_within_distance(&self, position: &Vec2, radius: f64) -> Vec<Neighbor> {
+        let mut neighbors: Vec<Neighbor> = self
+            .rtree
+            .locate_within_distance(position.as_point2(), radius * radius)
+            .map(|e| Neighbor::new(e.key, e.position, position))
+            .collect();
+        neighbors.sort_unstable_by_key(|n| n.key);
+        neighbors
+    }
+
+    fn nearest_neighbors(&self, position: &Vec2, k: usize, radius: f64) -> Vec<Neighbor> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut neighbors = vec![];
+        let mut cutoff = radius * radius;
+        for (entry, distance_2) in self
+            .rtree
+            .nearest_neighbor_iter_with_distance_2(&position.as_point2())
+        {
+            if distance_2 > cutoff {
+                break;
+            }
+            neighbors.push(Neighbor::new(entry.key, entry.position, position));
+            // Keep going through ties with the k-th entry so the key decides
+            // which of them make the cut, not the tree layout.
+            if neighbors.len() == k {
+                cutoff = distance_2;
+            }
+        }
+
+        sort_nearest(&mut neighbors);
+        neighbors.truncate(k);
+        neighbors
+    }
+}
+
+type Cell = (i64, i64);
+
+/// Upper bound on the dense part of a `GridIndex`, so tiny cells on a huge
+/// canvas don't allocate unbounded memory.
+const MAX_DENSE_CELLS: usize = 1 << 20;
+
+/// Smallest cell a `GridIndex` uses, well below any useful interaction
+/// radius. Zero radii would otherwise make every cell coordinate overflow.
+const MIN_CELL_SIZE: f64 = 1e-3;
+
+/// Uniform grid spatial hash. Cells covering the canvas are stored densely
+/// and anything outside spills into a hash map. Each cell is kept sorted by
+/// key, so edits are cheap and a query visits the cells overlapping the
+/// search square in a fixed order.
+pub struct GridIndex {
+    cell_size: f64,
+    cols: i64,
+    rows: i64,
+    dense: Vec<Vec<IndexEntry>>,
+    sparse: FxHashMap<Cell, Vec<IndexEntry>>,
+    len: usize,
+}
+
+impl GridIndex {
+    /// A grid with no dense region, suitable when the extent is unknown.
+    pub fn new(cell_size: f64) -> Self {
+        Self::with_extent(cell_size, 0.0, 0.0)
+    }
+
+    /// A grid whose cells are stored densely over `[0, width] x [0, height]`.
+    pub fn with_extent(cell_size: f64, width: f64, height: f64) -> Self {
+        let cell_size = cell_size.max(MIN_CELL_SIZE);
+        // Sized in floating point, since the cell counts can overflow before
+        // they are compared against the limit.
+        let cols = (width / cell_size).ceil() + 1.0;
+        let rows = (height / cell_size).ceil() + 1.0;
+        let (cols, rows) = if width > 0.0 && height > 0.0 && cols * rows <= MAX_DENSE_CELLS as f64 {
+            (cols as i64, rows as i64)
+        } else {
+            (0, 0)
+        };
+
+        Self {
+            cell_size,
+            cols,
+            rows,
+            dense: vec![vec![]; (cols * rows) as usize],
+            sparse: FxHashMap::default(),
+            len: 0,
+        }
+    }
+
+    /// Sized so that a query at the largest interaction radius touches at
+    /// most a 3x3 block of cells.
+    pub fn for_settings(settings: &Settings) -> Self {
+        Self::with_extent(
+            settings
+                .separation_distance
+                .max(settings.attraction_distance),
+            settings.width as f64,
+            settings.height as f64,
+        )
+    }
+
+    fn cell_of(&self, point: &Point2) -> Cell {
+        (
+            (point[0] / self.cell_size).floor() as i64,
+            (point[1] / self.cell_size).floor() as i64,
+        )
+    }
+
+    fn dense_index(&self, (col, row): Cell) -> Option<usize> {
+        if col >= 0 && row >= 0 && col < self.cols && row < self.rows {
+            Some((row * self.cols + col) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn cell(&self, cell: Cell) -> Option<&Vec<IndexEntry>> {
+        match self.dense_index(cell) {
+            Some(i) => Some(&self.dense[i]),
+            None => self.sparse.get(&cell),
+        }
+    }
+
+    fn cell_mut(&mut self, cell: Cell) -> &mut Vec<IndexEntry> {
+        match self.dense_index(cell) {
+            Some(i) => &mut self.dense[i],
+            None => self.sparse.entry(cell).or_default(),
+        }
+    }
+
+    fn remove_from_cell(&mut self, cell: Cell, key: NodeKey) -> bool {
+        let entries = self.cell_mut(cell);
+        let removed = match entries.binary_search_by_key(&key, |e| e.key) {
+            Ok(i) => {
+                entries.remove(i);
+                true
+            }
+            Err(_) => false,
+        };
+        if entries.is_empty() {
+            self.sparse.remove(&cell);
+        }
+        removed
+    }
+
+    fn entries_in_square(&self, position: &Vec2, radius: f64) -> Vec<&IndexEntry> {
+        let bottom_corner = *position - radius;
+        let top_corner = *position + radius;
+        let (min_col, min_row) = self.cell_of(&bottom_corner.as_point2());
+        let (max_col, max_row) = self.cell_of(&top_corner.as_point2());
+        let in_square = |p: &Point2| {
+            p[0] >= bottom_corner.x
+                && p[0] <= top_corner.x
+                && p[1] >= bottom_corner.y
+                && p[1] <= top_corner.y
+        };
+
+        // Huge radii would visit more empty cells than there are stored
+        // ones, so walk the stored cells instead.
+        let span = (max_col - min_col + 1) as f64 * (max_row - min_row + 1) as f64;
+        if span > (self.dense.len() + self.sparse.len()) as f64 {
+            let mut entries: Vec<&IndexEntry> = self
+                .dense
+                .iter()
+                .chain(self.sparse.values())
+                .flatten()
+                .filter(|e| in_square(&e.position))
+                .collect();
+            entries.sort_unstable_by_key(|e| e.key);
+            return entries;
+        }
+
+        let mut neighbors = vec![];
+        for row in min_row..=max_row {
+            for col in min_col..=max_col {
+                if let Some(entries) = self.cell((col, row)) {
+                    for entry in entries.iter() {
+                        if in_square(&entry.position) {
+                            neighbors.push(entry);
+                        }
+                    }
+                }
+            }
+        }
+
+        neighbors
+    }
+}
+
+impl SpatialIndex for GridIndex {
+    fn index(&mut self, mut entries: Vec<IndexEntry>) {
+        for cell in self.dense.iter_mut() {
+            cell.clear();
+        }
+        self.sparse.clear();
+        self.len = entries.len();
+
+        // Pushing in key order leaves every cell sorted.
+        entries.sort_unstable_by_key(|e| e.key);
+        for entry in entries {
+            let cell = self.cell_of(&entry.position);
+            self.cell_mut(cell).push(entry);
+        }
+    }
+
+    fn insert(&mut self, entry: IndexEntry) {
+        let cell = self.cell_of(&entry.position);
+        let entries = self.cell_mut(cell);
+        match entries.binary_search_by_key(&entry.key, |e| e.key) {
+            Ok(i) => entries[i] = entry,
+            Err(i) => {
+                entries.insert(i, entry);
+                self.len += 1;
+            }
+        }
+    }
+
+    fn remove(&mut self, entry: &IndexEntry) {
+        let cell = self.cell_of(&entry.position);
+        if self.remove_from_cell(cell, entry.key) {
+            self.len -= 1;
+        }
+    }
+
+    fn move_entry(&mut self, entry: &IndexEntry, to: Point2) {
+        let from_cell = self.cell_of(&entry.position);
+        if from_cell == self.cell_of(&to) {
+            let entries = self.cell_mut(from_cell);
+            if let Ok(i) = entries.binary_search_by_key(&entry.key, |e| e.key) {
+                entries[i].position = to;
+                return;
+            }
+        }
+
+        self.remove(entry);
+        self.insert(IndexEntry::new(entry.key, to));
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get_neighbors(&self, position: &Vec2, radius: f64) -> Vec<&Point2> {
+        self.entries_in_square(position, radius)
+            .into_iter()
+            .map(|e| &e.position)
+            .collect()
+    }
+
+    fn locate_within_distance(&self, position: &Vec2, radius: f64) -> Vec<Neighbor> {
+        self.entries_in_square(position, radius)
             .into_iter()
+            .map(|e| Neighbor::new(e.key, e.position, position))
+            .filter(|n| n.distance <= radius)
             .collect()
     }
 }
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum SpatialIndexType {
     None,
     RTree,
+    /// The default, since moving a node within its cell is a single write
+    /// and the simulation moves most nodes every frame.
+    #[default]
+    Grid,
 }
 
-pub fn get_spatial_index(index_type: SpatialIndexType) -> Box<dyn SpatialIndex> {
+pub fn get_spatial_index(
+    index_type: SpatialIndexType,
+    settings: &Settings,
+) -> Box<dyn SpatialIndex> {
     match index_type {
         SpatialIndexType::None => Box::new(NoIndex::new()),
         SpatialIndexType::RTree => Box::new(RTreeIndex::new()),
+        SpatialIndexType::Grid => Box::new(GridIndex::for_settings(settings)),
     }
 }
 
-pub fn index_points(points: Vec<Point2>, index_type: SpatialIndexType) -> Box<dyn SpatialIndex> {
-    let mut spatial_index = get_spatial_index(index_type);
-    spatial_index.index(points);
+pub fn index_entries(
+    entries: Vec<IndexEntry>,
+    index_type: SpatialIndexType,
+    settings: &Settings,
+) -> Box<dyn SpatialIndex> {
+    let mut spatial_index = get_spatial_index(index_type, settings);
+    spatial_index.index(entries);
     spatial_index
 }
+
+/// Indexes loose points, keyed by their position in `points`.
+pub fn index_points(
+    points: Vec<Point2>,
+    index_type: SpatialIndexType,
+    settings: &Settings,
+) -> Box<dyn SpatialIndex> {
+    index_entries(point_entries(points), index_type, settings)
+}
+
+pub fn point_entries(points: Vec<Point2>) -> Vec<IndexEntry> {
+    points
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            IndexEntry::new(
+                NodeKey {
+                    path: 0,
+                    node: i as u32,
+                },
+                p,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Settings;
+    use crate::spatial_index::*;
+
+    const INDEX_TYPES: [SpatialIndexType; 3] = [
+        SpatialIndexType::None,
+        SpatialIndexType::RTree,
+        SpatialIndexType::Grid,
+    ];
+
+    fn sorted(mut points: Vec<Point2>) -> Vec<Point2> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    fn neighbors(index: &dyn SpatialIndex, position: Vec2, radius: f64) -> Vec<Point2> {
+        index
+            .get_neighbors(&position, radius)
+            .into_iter()
+            .copied()
+            .collect()
+    }
+
+    fn scattered_points() -> Vec<Point2> {
+        let mut points = vec![];
+        for i in 0..40 {
+            for j in 0..30 {
+                let x = i as f64 * 7.3 + (j as f64 * 1.7).sin() * 3.0;
+                let y = j as f64 * 9.1 + (i as f64 * 2.3).cos() * 4.0;
+                points.push([x, y]);
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn grid_index_matches_rtree() {
+        let settings = Settings::new(400, 400);
+        let points = scattered_points();
+        let grid = index_points(points.clone(), SpatialIndexType::Grid, &settings);
+        let rtree = index_points(points.clone(), SpatialIndexType::RTree, &settings);
+
+        for (position, radius) in [
+            (Vec2::new(100.0, 100.0), 20.0),
+            (Vec2::new(0.0, 0.0), 50.0),
+            (Vec2::new(290.0, 270.0), 70.0),
+            (Vec2::new(-100.0, 50.0), 10.0),
+            (Vec2::new(150.0, 120.0), 500.0),
+        ] {
+            assert_eq!(
+                sorted(neighbors(grid.as_ref(), position, radius)),
+                sorted(neighbors(rtree.as_ref(), position, radius))
+            );
+        }
+    }
+
+    #[test]
+    fn grid_index_sparse_outlier() {
+        let mut index = GridIndex::new(1.0);
+        index.index(point_entries(vec![[0.0, 0.0], [0.5, 0.5], [1.0e9, 1.0e9]]));
+
+        assert_eq!(index.get_neighbors(&Vec2::new(0.0, 0.0), 1.0).len(), 2);
+        assert_eq!(index.get_neighbors(&Vec2::new(1.0e9, 1.0e9), 1.0).len(), 1);
+    }
+
+    #[test]
+    fn grid_index_tiny_cells() {
+        for cell_size in [0.0, 1e-9, 1e-3] {
+            let mut index = GridIndex::with_extent(cell_size, 1920.0, 1080.0);
+            index.index(point_entries(vec![[0.0, 0.0], [1000.0, 500.0]]));
+            assert_eq!(index.get_neighbors(&Vec2::new(1000.0, 500.0), 1.0).len(), 1);
+        }
+
+        let settings = Settings {
+            separation_distance: 0.0,
+            attraction_distance: 0.0,
+            ..Settings::new(400, 400)
+        };
+        assert_eq!(GridIndex::for_settings(&settings).len(), 0);
+    }
+
+    #[test]
+    fn grid_index_empty() {
+        let mut index = GridIndex::new(10.0);
+        index.index(vec![]);
+        assert!(index.get_neighbors(&Vec2::new(0.0, 0.0), 5.0).is_empty());
+    }
+
+    #[test]
+    fn spatial_index_incremental_matches_rebuild() {
+        let settings = Settings::new(400, 400);
+        let points = scattered_points();
+
+        let mut changes = IndexChanges::new();
+        let mut expected = point_entries(points.clone());
+        for e in expected.iter_mut().step_by(3) {
+            let from = Vec2::from_point2(&e.position);
+            let to = from + Vec2::new(11.5, -4.0);
+            changes.move_node(e.key.node, from, to);
+            e.position = to.as_point2();
+        }
+        for e in expected.iter().skip(1).step_by(7) {
+            changes.remove(e.key.node, Vec2::from_point2(&e.position));
+        }
+        changes.insert(5000, Vec2::new(33.0, 44.0));
+
+        expected.retain(|e| e.key.node % 7 != 1);
+        expected.push(IndexEntry::new(
+            NodeKey {
+                path: 0,
+                node: 5000,
+            },
+            [33.0, 44.0],
+        ));
+
+        for index_type in INDEX_TYPES {
+            let mut incremental = index_points(points.clone(), index_type, &settings);
+            incremental.apply(changes.changes());
+            let rebuilt = index_entries(expected.clone(), index_type, &settings);
+
+            assert_eq!(incremental.len(), expected.len());
+            for position in [Vec2::new(100.0, 100.0), Vec2::new(40.0, 40.0)] {
+                assert_eq!(
+                    neighbors(incremental.as_ref(), position, 30.0),
+                    neighbors(rebuilt.as_ref(), position, 30.0)
+                );
+            }
+        }
+    }
+
+    fn brute_force_within(points: &[Point2], position: Vec2, radius: f64) -> Vec<Neighbor> {
+        point_entries(points.to_vec())
+            .into_iter()
+            .map(|e| Neighbor::new(e.key, e.position, &position))
+            .filter(|n| n.distance <= radius)
+            .collect()
+    }
+
+    #[test]
+    fn spatial_index_locate_within_distance() {
+        let settings = Settings::new(400, 400);
+        let points = scattered_points();
+
+        for index_type in INDEX_TYPES {
+            let index = index_points(points.clone(), index_type, &settings);
+            for (position, radius) in [
+                (Vec2::new(100.0, 100.0), 20.0),
+                (Vec2::new(0.0, 0.0), 50.0),
+                (Vec2::new(-100.0, 50.0), 10.0),
+            ] {
+                let mut found = index.locate_within_distance(&position, radius);
+                found.sort_unstable_by_key(|n| n.key);
+                assert_eq!(found, brute_force_within(&points, position, radius));
+            }
+        }
+    }
+
+    #[test]
+    fn spatial_index_nearest_neighbors() {
+        let settings = Settings::new(400, 400);
+        let mut points = scattered_points();
+        // Equidistant from the query point, so only the key tie-break
+        // decides which of them are returned.
+        points.extend([
+            [210.0, 150.0],
+            [190.0, 150.0],
+            [200.0, 160.0],
+            [200.0, 140.0],
+        ]);
+        let position = Vec2::new(200.0, 150.0);
+
+        for k in [0, 1, 3, 10] {
+            let mut expected = brute_force_within(&points, position, 30.0);
+            sort_nearest(&mut expected);
+            expected.truncate(k);
+
+            for index_type in INDEX_TYPES {
+                let index = index_points(points.clone(), index_type, &settings);
+                assert_eq!(index.nearest_neighbors(&position, k, 30.0), expected);
+            }
+        }
+
+        let mut neighbors = brute_force_within(&points, position, 30.0);
+        let key = NodeKey { path: 1, node: 0 };
+        neighbors.push(Neighbor::new(key, [f64::NAN, 0.0], &position));
+        sort_nearest(&mut neighbors);
+        let distances: Vec<f64> = neighbors
+            .iter()
+            .map(|n| n.distance)
+            .filter(|d| !d.is_nan())
+            .collect();
+        assert!(distances.windows(2).all(|d| d[0] <= d[1]));
+    }
+}
@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "web")]
+use crate::draw::draw_path;
+use crate::export::PATH_COLOR;
+use crate::geometry::turn_angle;
+use crate::node::Node;
+use crate::path::Path;
+use crate::raster::{parse_hex_color, Rgb};
+use crate::spatial_index::{RTreeIndex, SpatialIndex};
+use crate::torus::Torus;
+use crate::vec2::Vec2;
+
+/// A per-node quantity that color or width can follow.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum StyleSource {
+    /// Distance along the path, from its first node to its last.
+    ArcLength,
+    /// Frames since the node was created.
+    Age,
+    Generation,
+    /// How sharply the path turns at the node.
+    Curvature,
+    /// The node's speed.
+    Velocity,
+    /// How many other nodes lie within `density_radius`.
+    Density,
+    /// One of the node's user scalars.
+    Scalar(usize),
+}
+
+/// How paths are colored and stroked, on the canvas and in exports.
+///
+/// Except for `ArcLength`, which runs from 0 to 1 along each path, sources
+/// are scaled so that their smallest value in the frame maps to the start of
+/// the palette or `min_width` and the largest to the end or `max_width`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StyleConfig {
+    /// `#rrggbb` colors given to the paths in turn.
+    pub path_colors: Vec<String>,
+    /// Colors paths through `palette` by this source instead.
+    #[serde(default)]
+    pub color_by: Option<StyleSource>,
+    /// Evenly spaced `#rrggbb` stops.
+    pub palette: Vec<String>,
+    pub line_width: f64,
+    /// Varies the width between `min_width` and `max_width` by this source
+    /// instead of using `line_width`.
+    #[serde(default)]
+    pub width_by: Option<StyleSource>,
+    pub min_width: f64,
+    pub max_width: f64,
+    pub density_radius: f64,
+}
+
+impl StyleConfig {
+    pub fn new() -> Self {
+        Self {
+            path_colors: vec![PATH_COLOR.to_string()],
+            color_by: None,
+            palette: vec![
+                "#440154".to_string(),
+                "#21918c".to_string(),
+                "#fde725".to_string(),
+            ],
+            line_width: 1.0,
+            width_by: None,
+            min_width: 0.5,
+            max_width: 3.0,
+            density_radius: 20.0,
+        }
+    }
+}
+
+impl Default for StyleConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A run of a path that is drawn with a single color and width.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledPolyline {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+    pub color: Rgb,
+    pub width: f64,
+}
+
+impl StyledPolyline {
+    /// The polyline folded onto the view of `torus`, split where it crosses
+    /// an edge.
+    pub fn wrapped(&self, torus: &Torus) -> Vec<Self> {
+        torus
+            .split_polyline(&self.points, self.closed)
+            .into_iter()
+            .map(|(points, closed)| Self {
+                points,
+                closed,
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "web")]
+impl StyledPolyline {
+    pub fn draw(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        let color = format_hex_color(self.color);
+        draw_path(ctx, &self.points, self.closed, &color, self.width);
+    }
+}
+
+pub fn format_hex_color(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// The color at `t` between 0 and 1 along evenly spaced `stops`.
+pub fn sample_palette(stops: &[Rgb], t: f64) -> Rgb {
+    match stops {
+        [] => parse_hex_color(PATH_COLOR),
+        [only] => *only,
+        _ => {
+            let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+            let index = (position.floor() as usize).min(stops.len() - 2);
+            let frac = position - index as f64;
+            let (a, b) = (stops[index], stops[index + 1]);
+            let mut color = [0; 3];
+            for (i, channel) in color.iter_mut().enumerate() {
+                *channel = (a[i] as f64 + (b[i] as f64 - a[i] as f64) * frac).round() as u8;
+            }
+            color
+        }
+    }
+}
+
+/// The raw value of `source` for every node, path by path.
+fn raw_values(
+    paths: &[Path],
+    source: StyleSource,
+    config: &StyleConfig,
+    frame: u64,
+) -> Vec<Vec<f64>> {
+    let density_index = match source {
+        StyleSource::Density => {
+            let mut index = RTreeIndex::new();
+            index.index(
+                paths
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, p)| p.index_entries(i))
+                    .collect(),
+            );
+            Some(index)
+        }
+        _ => None,
+    };
+
+    paths
+        .iter()
+        .map(|path| {
+            let nodes = path.nodes();
+            let n = nodes.len();
+            let per_node = |value: &dyn Fn(&Node) -> f64| nodes.iter().map(value).collect();
+            match source {
+                StyleSource::ArcLength => {
+                    let mut lengths = vec![0.0; n];
+                    for i in 1..n {
+                        lengths[i] = lengths[i - 1] + nodes[i - 1].distance(&nodes[i]);
+                    }
+                    let total = lengths.last().copied().unwrap_or(0.0);
+                    if total > 0.0 {
+                        lengths.iter_mut().for_each(|l| *l /= total);
+                    }
+                    lengths
+                }
+                StyleSource::Curvature => (0..n)
+                    .map(|i| {
+                        let has_neighbors = path.is_cyclic() || (i > 0 && i + 1 < n);
+                        if n < 3 || !has_neighbors {
+                            return 0.0;
+                        }
+                        let prev = nodes[(i + n - 1) % n].position;
+                        let next = nodes[(i + 1) % n].position;
+                        turn_angle(prev, nodes[i].position, next)
+                    })
+                    .collect(),
+                StyleSource::Age => per_node(&|node| node.age(frame) as f64),
+                StyleSource::Generation => per_node(&|node| node.generation as f64),
+                StyleSource::Velocity => per_node(&|node| node.velocity.length()),
+                StyleSource::Density => {
+                    let index = density_index.as_ref().unwrap();
+                    per_node(&|node| {
+                        let near =
+                            index.locate_within_distance(&node.position, config.density_radius);
+                        near.len().saturating_sub(1) as f64
+                    })
+                }
+                StyleSource::Scalar(slot) => {
+                    per_node(&|node| node.scalars.get(slot).copied().unwrap_or(0.0))
+                }
+            }
+        })
+        .collect()
+}
+
+/// The value of `source` for every node, scaled to lie between 0 and 1.
+pub fn node_values(
+    paths: &[Path],
+    source: StyleSource,
+    config: &StyleConfig,
+    frame: u64,
+) -> Vec<Vec<f64>> {
+    let mut values = raw_values(paths, source, config, frame);
+    if source == StyleSource::ArcLength {
+        return values;
+    }
+
+    let all = values.iter().flatten();
+    let min = all.clone().copied().fold(f64::INFINITY, f64::min);
+    let max = all.copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    for value in values.iter_mut().flatten() {
+        *value = if range > 0.0 {
+            (*value - min) / range
+        } else {
+            0.0
+        };
+    }
+    values
+}
+
+/// Splits `paths` into runs of equal color and width. Each edge is styled by
+/// the average of its ends, and a path whose edges all look the same stays
+/// in one piece.
+pub fn style_paths(paths: &[Path], config: &StyleConfig, frame: u64) -> Vec<StyledPolyline> {
+    let path_colors: Vec<Rgb> = config
+        .path_colors
+        .iter()
+        .map(|c| parse_hex_color(c))
+        .collect();
+    let palette: Vec<Rgb> = config.palette.iter().map(|c| parse_hex_color(c)).collect();
+    let colors = config
+        .color_by
+        .map(|source| node_values(paths, source, config, frame));
+    let widths = config
+        .width_by
+        .map(|source| node_values(paths, source, config, frame));
+
+    let mut polylines = vec![];
+    for (path_index, path) in paths.iter().enumerate() {
+        let nodes = path.nodes();
+        let n = nodes.len();
+        if n < 2 {
+            continue;
+        }
+
+        let path_color = match path_colors.len() {
+            0 => parse_hex_color(PATH_COLOR),
+            len => path_colors[path_index % len],
+        };
+        let edge_style = |a: usize, b: usize| {
+            let t = |values: &Vec<Vec<f64>>| (values[path_index][a] + values[path_index][b]) / 2.0;
+            let color = match &colors {
+                Some(values) => sample_palette(&palette, t(values)),
+                None => path_color,
+            };
+            let width = match &widths {
+                Some(values) => {
+                    config.min_width + (config.max_width - config.min_width) * t(values)
+                }
+                None => config.line_width,
+            };
+            (color, width)
+        };
+
+        let edges = if path.is_cyclic() && n > 2 { n } else { n - 1 };
+        let mut runs: Vec<StyledPolyline> = vec![];
+        for a in 0..edges {
+            let b = (a + 1) % n;
+            let (color, width) = edge_style(a, b);
+            match runs.last_mut() {
+                Some(run) if run.color == color && run.width == width => {
+                    run.points.push(nodes[b].position)
+                }
+                _ => runs.push(StyledPolyline {
+                    points: vec![nodes[a].position, nodes[b].position],
+                    closed: false,
+                    color,
+                    width,
+                }),
+            }
+        }
+
+        // A loop drawn in one style closes on itself rather than on a copy
+        // of its first point.
+        if edges == n && runs.len() == 1 {
+            runs[0].points.pop();
+            runs[0].closed = true;
+        }
+        polylines.extend(runs);
+    }
+    polylines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::style::*;
+
+    fn path(points: &[(f64, f64)], cyclic: bool) -> Path {
+        let nodes = points
+            .iter()
+            .map(|(x, y)| Node::new_with_position(Vec2::new(*x, *y)))
+            .collect();
+        Path::new(nodes, cyclic)
+    }
+
+    #[test]
+    fn style_sample_palette() {
+        let stops = [[0, 0, 0], [255, 100, 0]];
+        assert_eq!(sample_palette(&stops, 0.0), [0, 0, 0]);
+        assert_eq!(sample_palette(&stops, 0.5), [128, 50, 0]);
+        assert_eq!(sample_palette(&stops, 2.0), [255, 100, 0]);
+        assert_eq!(sample_palette(&[], 0.5), [255, 255, 255]);
+        assert_eq!(format_hex_color([255, 8, 0]), "#ff0800");
+    }
+
+    #[test]
+    fn style_uniform_paths() {
+        let paths = vec![
+            path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], true),
+            path(&[(0.0, 20.0), (10.0, 20.0)], false),
+        ];
+        let mut config = StyleConfig::new();
+        config.path_colors = vec!["#ff0000".to_string(), "#00ff00".to_string()];
+
+        let lines = style_paths(&paths, &config, 0);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].closed);
+        assert_eq!(lines[0].points.len(), 3);
+        assert_eq!(lines[0].color, [255, 0, 0]);
+        assert!(!lines[1].closed);
+        assert_eq!(lines[1].color, [0, 255, 0]);
+        assert_eq!(lines[1].width, 1.0);
+    }
+
+    #[test]
+    fn style_gradients() {
+        let paths = vec![path(
+            &[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0), (30.0, 0.0)],
+            false,
+        )];
+        let mut config = StyleConfig::new();
+        config.color_by = Some(StyleSource::ArcLength);
+        config.palette = vec!["#000000".to_string(), "#ffffff".to_string()];
+
+        let lines = style_paths(&paths, &config, 0);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].color, [43, 43, 43]);
+        assert_eq!(lines[2].color, [212, 212, 212]);
+        assert_eq!(
+            lines[1].points,
+            vec![Vec2::new(10.0, 0.0), Vec2::new(20.0, 0.0)]
+        );
+
+        // Width by age, with the oldest edge widest.
+        let mut aged = paths[0].clone();
+        let births = [0, 0, 4, 8];
+        let nodes: Vec<Node> = aged
+            .nodes()
+            .iter()
+            .zip(births.iter())
+            .map(|(n, birth)| Node {
+                birth: *birth,
+                ..*n
+            })
+            .collect();
+        aged = Path::new(nodes, false);
+        let mut config = StyleConfig::new();
+        config.width_by = Some(StyleSource::Age);
+        let lines = style_paths(&[aged], &config, 10);
+        let widths: Vec<f64> = lines.iter().map(|l| l.width).collect();
+        assert_eq!(widths, vec![3.0, 2.375, 1.125]);
+    }
+
+    #[test]
+    fn style_node_values() {
+        let paths = vec![
+            path(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0)], false),
+            path(&[(100.0, 100.0), (200.0, 100.0)], false),
+        ];
+        let config = StyleConfig::new();
+
+        let curvature = node_values(&paths, StyleSource::Curvature, &config, 0);
+        assert_eq!(curvature[0], vec![0.0, 0.0, 1.0, 0.0]);
+
+        let density = node_values(&paths, StyleSource::Density, &config, 0);
+        assert_eq!(density[0], vec![1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(density[1], vec![0.0, 0.0]);
+    }
+}
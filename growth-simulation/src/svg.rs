@@ -0,0 +1,147 @@
+use std::fmt::Write;
+
+use crate::bounds::Bounds;
+use crate::export::*;
+use crate::style::{format_hex_color, StyledPolyline};
+use crate::utils::format_coord;
+use crate::vec2::Vec2;
+
+/// Builds the `d` attribute for a polyline, closing it with `Z` when cyclic.
+pub fn path_data(points: &[Vec2], cyclic: bool) -> String {
+    let mut data = String::new();
+
+    for (index, point) in points.iter().enumerate() {
+        let command = if index == 0 { "M" } else { "L" };
+        if index > 0 {
+            data.push(' ');
+        }
+        write!(
+            data,
+            "{}{} {}",
+            command,
+            format_coord(point.x),
+            format_coord(point.y)
+        )
+        .unwrap();
+    }
+
+    if cyclic && !points.is_empty() {
+        data.push_str(" Z");
+    }
+
+    data
+}
+
+fn write_path(svg: &mut String, points: &[Vec2], cyclic: bool, color: &str, width: f64) {
+    if points.len() < 2 {
+        return;
+    }
+
+    writeln!(
+        svg,
+        r#"  <path d="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+        path_data(points, cyclic),
+        color,
+        format_coord(width)
+    )
+    .unwrap();
+}
+
+pub fn write_svg(
+    width: u32,
+    height: u32,
+    lines: &[StyledPolyline],
+    bounds: &dyn Bounds,
+    options: ExportOptions,
+) -> String {
+    let mut svg = String::new();
+
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    )
+    .unwrap();
+
+    if options.include_background {
+        writeln!(
+            svg,
+            r#"  <rect x="0" y="0" width="{}" height="{}" fill="{}"/>"#,
+            width, height, BACKGROUND_COLOR
+        )
+        .unwrap();
+    }
+
+    for line in lines.iter() {
+        write_path(
+            &mut svg,
+            &line.points,
+            line.closed,
+            &format_hex_color(line.color),
+            line.width,
+        );
+    }
+
+    if options.include_bounds {
+        for outline in bounds.outline() {
+            write_path(&mut svg, &outline, true, BOUNDS_COLOR, 1.0);
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::*;
+    use crate::node::Node;
+    use crate::path::Path;
+    use crate::style::{style_paths, StyleConfig};
+    use crate::svg::*;
+
+    fn triangle(cyclic: bool) -> Path {
+        let nodes = vec![
+            Node::new_with_position(Vec2::new(0.0, 0.0)),
+            Node::new_with_position(Vec2::new(10.5, 0.0)),
+            Node::new_with_position(Vec2::new(0.0, 2.25)),
+        ];
+        Path::new(nodes, cyclic)
+    }
+
+    #[test]
+    fn svg_path_data() {
+        let open = triangle(false);
+        let closed = triangle(true);
+        assert_eq!(
+            path_data(&open.node_positions(), false),
+            "M0 0 L10.5 0 L0 2.25"
+        );
+        assert_eq!(
+            path_data(&closed.node_positions(), true),
+            "M0 0 L10.5 0 L0 2.25 Z"
+        );
+    }
+
+    #[test]
+    fn svg_write_svg() {
+        let bounds = RectBounds::new(100.0, 100.0, 50.0, 50.0);
+        let lines = style_paths(&[triangle(true)], &StyleConfig::new(), 0);
+
+        let svg = write_svg(100, 100, &lines, &bounds, ExportOptions::new());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<rect"));
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains(r##"stroke="#ffffff" stroke-width="1""##));
+
+        let options = ExportOptions {
+            include_bounds: true,
+            include_background: false,
+        };
+        let svg = write_svg(100, 100, &lines, &bounds, options);
+        assert!(!svg.contains("<rect"));
+        assert_eq!(svg.matches("<path").count(), 2);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
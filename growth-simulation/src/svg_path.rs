@@ -0,0 +1,528 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::config::{Settings, SvgConfig};
+use crate::vec2::Vec2;
+
+/// One continuous run of an SVG path, flattened to a polyline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subpath {
+    pub points: Vec<Vec2>,
+    /// Ended with `Z`. The closing point is not repeated in `points`.
+    pub closed: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SvgPathError {
+    /// Coordinates appeared before the first command.
+    MissingCommand(usize),
+    UnknownCommand(char, usize),
+    /// A command was missing some of its arguments.
+    ExpectedNumber(usize),
+}
+
+impl fmt::Display for SvgPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvgPathError::MissingCommand(at) => {
+                write!(f, "path data must start with a command (at {})", at)
+            }
+            SvgPathError::UnknownCommand(command, at) => {
+                write!(f, "unsupported path command '{}' (at {})", command, at)
+            }
+            SvgPathError::ExpectedNumber(at) => write!(f, "expected a number (at {})", at),
+        }
+    }
+}
+
+impl std::error::Error for SvgPathError {}
+
+struct Tokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn skip_separators(&mut self) {
+        while self.pos < self.data.len()
+            && (self.data[self.pos].is_ascii_whitespace() || self.data[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos >= self.data.len()
+    }
+
+    fn command(&mut self) -> Option<char> {
+        self.skip_separators();
+        match self.data.get(self.pos) {
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.pos += 1;
+                Some(*c as char)
+            }
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, SvgPathError> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |tokens: &mut Self| {
+            let from = tokens.pos;
+            while tokens.pos < tokens.data.len() && tokens.data[tokens.pos].is_ascii_digit() {
+                tokens.pos += 1;
+            }
+            tokens.pos > from
+        };
+
+        if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut has_digits = digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            has_digits |= digits(self);
+        }
+        if !has_digits {
+            self.pos = start;
+            return Err(SvgPathError::ExpectedNumber(start));
+        }
+
+        if matches!(self.data.get(self.pos), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mantissa_end;
+            }
+        }
+
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(SvgPathError::ExpectedNumber(start))
+    }
+
+    /// Arc flags are a single `0` or `1` and need no separator after them.
+    fn flag(&mut self) -> Result<bool, SvgPathError> {
+        self.skip_separators();
+        match self.data.get(self.pos) {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(SvgPathError::ExpectedNumber(self.pos)),
+        }
+    }
+
+    fn point(&mut self) -> Result<Vec2, SvgPathError> {
+        Ok(Vec2::new(self.number()?, self.number()?))
+    }
+}
+
+/// Collects subpaths while the path data is walked.
+struct Builder {
+    max_edge_length: f64,
+    subpaths: Vec<Subpath>,
+    points: Vec<Vec2>,
+    start: Vec2,
+    current: Vec2,
+}
+
+impl Builder {
+    fn finish(&mut self, closed: bool) {
+        let mut points = std::mem::take(&mut self.points);
+        if closed && points.len() > 1 && points[0].distance(&points[points.len() - 1]) < 1e-9 {
+            points.pop();
+        }
+        if points.len() > 1 {
+            self.subpaths.push(Subpath { points, closed });
+        }
+    }
+
+    fn move_to(&mut self, point: Vec2) {
+        self.finish(false);
+        self.points.push(point);
+        self.start = point;
+        self.current = point;
+    }
+
+    fn line_to(&mut self, point: Vec2) {
+        if self.points.is_empty() {
+            self.points.push(self.current);
+        }
+        if point != self.current {
+            self.points.push(point);
+        }
+        self.current = point;
+    }
+
+    fn close(&mut self) {
+        let start = self.start;
+        self.line_to(start);
+        self.finish(true);
+        self.current = start;
+    }
+
+    /// How many pieces to split a curve into so that no piece is longer than
+    /// `max_edge_length`. `length` must not underestimate the curve.
+    fn segments(&self, length: f64) -> usize {
+        ((length / self.max_edge_length).ceil() as usize).clamp(1, 10_000)
+    }
+
+    fn cubic_to(&mut self, c1: Vec2, c2: Vec2, end: Vec2) {
+        let start = self.current;
+        let n = self.segments(start.distance(&c1) + c1.distance(&c2) + c2.distance(&end));
+        for i in 1..=n {
+            let t = i as f64 / n as f64;
+            let s = 1.0 - t;
+            let point = start * (s * s * s)
+                + c1 * (3.0 * s * s * t)
+                + c2 * (3.0 * s * t * t)
+                + end * (t * t * t);
+            self.line_to(point);
+        }
+    }
+
+    fn quadratic_to(&mut self, control: Vec2, end: Vec2) {
+        let start = self.current;
+        let n = self.segments(start.distance(&control) + control.distance(&end));
+        for i in 1..=n {
+            let t = i as f64 / n as f64;
+            let s = 1.0 - t;
+            let point = start * (s * s) + control * (2.0 * s * t) + end * (t * t);
+            self.line_to(point);
+        }
+    }
+
+    /// Converts the endpoint arc to its center form, following the SVG
+    /// implementation notes, and samples it by angle.
+    fn arc_to(&mut self, radii: Vec2, rotation: f64, large_arc: bool, sweep: bool, end: Vec2) {
+        let start = self.current;
+        let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+        if rx == 0.0 || ry == 0.0 || start == end {
+            self.line_to(end);
+            return;
+        }
+
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let half = (start - end) / 2.0;
+        let x1 = cos * half.x + sin * half.y;
+        let y1 = -sin * half.x + cos * half.y;
+
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+        if large_arc == sweep {
+            coefficient = -coefficient;
+        }
+        let cx1 = coefficient * rx * y1 / ry;
+        let cy1 = -coefficient * ry * x1 / rx;
+        let mid = (start + end) / 2.0;
+        let center = Vec2::new(cos * cx1 - sin * cy1 + mid.x, sin * cx1 + cos * cy1 + mid.y);
+
+        let angle = |ux: f64, uy: f64| uy.atan2(ux);
+        let theta = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut delta = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry) - theta;
+        if sweep && delta < 0.0 {
+            delta += 2.0 * PI;
+        } else if !sweep && delta > 0.0 {
+            delta -= 2.0 * PI;
+        }
+
+        let n = self.segments(delta.abs() * rx.max(ry));
+        for i in 1..n {
+            let t = theta + delta * i as f64 / n as f64;
+            let (x, y) = (rx * t.cos(), ry * t.sin());
+            self.line_to(center + Vec2::new(cos * x - sin * y, sin * x + cos * y));
+        }
+        self.line_to(end);
+    }
+}
+
+/// Parses SVG path data (`M`, `L`, `H`, `V`, `C`, `S`, `Q`, `T`, `A` and `Z`,
+/// absolute or relative) into polylines. Curves and arcs are flattened into
+/// pieces no longer than `max_edge_length`; straight lines are kept whole.
+pub fn parse_path_data(data: &str, max_edge_length: f64) -> Result<Vec<Subpath>, SvgPathError> {
+    let mut tokens = Tokens {
+        data: data.as_bytes(),
+        pos: 0,
+    };
+    let mut builder = Builder {
+        max_edge_length: max_edge_length.max(f64::EPSILON),
+        subpaths: vec![],
+        points: vec![],
+        start: Vec2::new(0.0, 0.0),
+        current: Vec2::new(0.0, 0.0),
+    };
+    let mut command: Option<char> = None;
+    // The second control point of the last curve, for `S` and `T`.
+    let mut last_control: Option<(char, Vec2)> = None;
+
+    while !tokens.at_end() {
+        let at = tokens.pos;
+        let current = match tokens.command() {
+            Some(c) => c,
+            None => match command {
+                // Extra coordinate pairs after a moveto are treated as linetos.
+                Some('M') => 'L',
+                Some('m') => 'l',
+                Some('Z' | 'z') | None => return Err(SvgPathError::MissingCommand(at)),
+                Some(c) => c,
+            },
+        };
+        command = Some(current);
+
+        let kind = current.to_ascii_uppercase();
+        let relative = current.is_ascii_lowercase();
+        let origin = if relative {
+            builder.current
+        } else {
+            Vec2::new(0.0, 0.0)
+        };
+        let mut control = None;
+
+        match kind {
+            'M' => builder.move_to(origin + tokens.point()?),
+            'L' => builder.line_to(origin + tokens.point()?),
+            'H' => {
+                let x = tokens.number()? + origin.x;
+                builder.line_to(Vec2::new(x, builder.current.y));
+            }
+            'V' => {
+                let y = tokens.number()? + origin.y;
+                builder.line_to(Vec2::new(builder.current.x, y));
+            }
+            'C' | 'S' => {
+                let c1 = if kind == 'C' {
+                    origin + tokens.point()?
+                } else {
+                    match last_control {
+                        Some(('C', c)) => builder.current * 2.0 - c,
+                        _ => builder.current,
+                    }
+                };
+                let c2 = origin + tokens.point()?;
+                let end = origin + tokens.point()?;
+                builder.cubic_to(c1, c2, end);
+                control = Some(('C', c2));
+            }
+            'Q' | 'T' => {
+                let c = if kind == 'Q' {
+                    origin + tokens.point()?
+                } else {
+                    match last_control {
+                        Some(('Q', c)) => builder.current * 2.0 - c,
+                        _ => builder.current,
+                    }
+                };
+                let end = origin + tokens.point()?;
+                builder.quadratic_to(c, end);
+                control = Some(('Q', c));
+            }
+            'A' => {
+                let radii = tokens.point()?;
+                let rotation = tokens.number()?;
+                let large_arc = tokens.flag()?;
+                let sweep = tokens.flag()?;
+                let end = origin + tokens.point()?;
+                builder.arc_to(radii, rotation, large_arc, sweep, end);
+            }
+            'Z' => builder.close(),
+            _ => return Err(SvgPathError::UnknownCommand(current, at)),
+        }
+
+        last_control = control;
+    }
+
+    builder.finish(false);
+    Ok(builder.subpaths)
+}
+
+/// The uniform scale and offset that centers a shape on the canvas.
+struct Fit {
+    scale: f64,
+    center: Vec2,
+    canvas_center: Vec2,
+}
+
+impl Fit {
+    /// Fits `subpaths` so their larger side spans `size`, if they have any
+    /// extent at all.
+    fn new(subpaths: &[Subpath], settings: &Settings, size: f64) -> Option<Self> {
+        let mut min = Vec2::new(f64::INFINITY, f64::INFINITY);
+        let mut max = Vec2::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for point in subpaths.iter().flat_map(|s| s.points.iter()) {
+            min = Vec2::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vec2::new(max.x.max(point.x), max.y.max(point.y));
+        }
+
+        let extent = (max.x - min.x).max(max.y - min.y);
+        if !extent.is_finite() || extent == 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            scale: size / extent,
+            center: (min + max) / 2.0,
+            canvas_center: Vec2::new(settings.width as f64, settings.height as f64) / 2.0,
+        })
+    }
+
+    fn apply(&self, subpaths: &mut [Subpath]) {
+        for point in subpaths.iter_mut().flat_map(|s| s.points.iter_mut()) {
+            *point = self.canvas_center + (*point - self.center) * self.scale;
+        }
+    }
+}
+
+/// The shapes described by `config`, flattened and placed on the canvas.
+pub fn svg_shapes(config: &SvgConfig, settings: &Settings) -> Result<Vec<Subpath>, SvgPathError> {
+    let subpaths = parse_path_data(&config.path_data, settings.max_edge_length)?;
+    if config.size <= 0.0 {
+        return Ok(subpaths);
+    }
+
+    match Fit::new(&subpaths, settings, config.size) {
+        Some(fit) => {
+            // Flatten again at the final scale so curve pieces come out no
+            // longer than `max_edge_length` on the canvas.
+            let mut subpaths =
+                parse_path_data(&config.path_data, settings.max_edge_length / fit.scale)?;
+            fit.apply(&mut subpaths);
+            Ok(subpaths)
+        }
+        None => Ok(subpaths),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::svg_path::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(&b) < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn svg_path_lines_and_close() {
+        let subpaths = parse_path_data("M0 0 L10 0 h5 v5 l-15,0 Z m20 20 10 0", 1.0).unwrap();
+        assert_eq!(
+            subpaths,
+            vec![
+                Subpath {
+                    points: vec![
+                        Vec2::new(0.0, 0.0),
+                        Vec2::new(10.0, 0.0),
+                        Vec2::new(15.0, 0.0),
+                        Vec2::new(15.0, 5.0),
+                        Vec2::new(0.0, 5.0),
+                    ],
+                    closed: true,
+                },
+                Subpath {
+                    points: vec![Vec2::new(20.0, 20.0), Vec2::new(30.0, 20.0)],
+                    closed: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn svg_path_compact_numbers() {
+        let subpaths = parse_path_data("M.5-1.5.5.5e1L1e1,0", 1.0).unwrap();
+        assert_eq!(
+            subpaths[0].points,
+            vec![
+                Vec2::new(0.5, -1.5),
+                Vec2::new(0.5, 5.0),
+                Vec2::new(10.0, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn svg_path_curves_respect_max_edge_length() {
+        let subpaths = parse_path_data(
+            "M0 0 C0 50 100 50 100 0 S200 -50 200 0 Q250 50 300 0 T400 0",
+            4.0,
+        )
+        .unwrap();
+        let points = &subpaths[0].points;
+        assert_close(points[0], Vec2::new(0.0, 0.0));
+        assert_close(points[points.len() - 1], Vec2::new(400.0, 0.0));
+        for pair in points.windows(2) {
+            assert!(pair[0].distance(&pair[1]) <= 4.0);
+        }
+    }
+
+    #[test]
+    fn svg_path_arc() {
+        // A half circle of radius 10 around (10, 0), bulging upwards.
+        let subpaths = parse_path_data("M0 0 A10 10 0 0 1 20 0", 1.0).unwrap();
+        let points = &subpaths[0].points;
+        assert_close(points[points.len() - 1], Vec2::new(20.0, 0.0));
+        for point in points.iter() {
+            assert!((point.distance(&Vec2::new(10.0, 0.0)) - 10.0).abs() < 1e-9);
+            assert!(point.y <= 1e-9);
+        }
+        assert!(points.len() > 30);
+
+        // Flags without separators, and radii too small to reach the end.
+        let compact = parse_path_data("M0 0a1 1 0 1020 0", 1.0).unwrap();
+        assert_close(
+            compact[0].points[compact[0].points.len() - 1],
+            Vec2::new(20.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn svg_path_errors() {
+        assert_eq!(
+            parse_path_data("10 10", 1.0),
+            Err(SvgPathError::MissingCommand(0))
+        );
+        assert_eq!(
+            parse_path_data("M0 0 X5 5", 1.0),
+            Err(SvgPathError::UnknownCommand('X', 5))
+        );
+        assert_eq!(
+            parse_path_data("M0 0 L5", 1.0),
+            Err(SvgPathError::ExpectedNumber(7))
+        );
+        assert_eq!(parse_path_data("", 1.0), Ok(vec![]));
+    }
+
+    #[test]
+    fn svg_path_fit_to_canvas() {
+        let settings = Settings::new(100, 100);
+        let config = SvgConfig {
+            path_data: "M0 0 L2 0 L2 1 Z".to_string(),
+            size: 50.0,
+        };
+        let shapes = svg_shapes(&config, &settings).unwrap();
+        assert_eq!(
+            shapes[0].points,
+            vec![
+                Vec2::new(25.0, 37.5),
+                Vec2::new(75.0, 37.5),
+                Vec2::new(75.0, 62.5)
+            ]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::segment_intersection;
+use crate::path::Path;
+use crate::spatial_index::{IndexChange, NodeKey, SpatialIndex};
+use crate::torus::Torus;
+use crate::vec2::Vec2;
+
+/// One edge of a path, running from the node at `start` to the next one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Edge {
+    pub path: usize,
+    /// Index of the edge's first node within its path.
+    pub start: usize,
+    pub from: NodeKey,
+    pub to: NodeKey,
+    pub a: Vec2,
+    pub b: Vec2,
+}
+
+impl Edge {
+    pub fn id(&self) -> EdgeRef {
+        EdgeRef {
+            path: self.path,
+            edge: self.start,
+        }
+    }
+
+    pub fn touches(&self, key: NodeKey) -> bool {
+        self.from == key || self.to == key
+    }
+
+    pub fn length(&self) -> f64 {
+        self.a.distance(&self.b)
+    }
+}
+
+/// Names an edge: edge `i` of a path runs from its node `i` to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EdgeRef {
+    pub path: usize,
+    pub edge: usize,
+}
+
+/// Two edges that cross, and where.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Crossing {
+    pub first: EdgeRef,
+    pub second: EdgeRef,
+    pub point: Vec2,
+}
+
+struct PathShape {
+    cyclic: bool,
+    ids: Vec<u32>,
+    positions: Vec<Vec2>,
+    index_of: FxHashMap<u32, usize>,
+    longest_edge: f64,
+}
+
+impl PathShape {
+    fn new(path: &Path) -> Self {
+        let nodes = path.nodes();
+        let mut shape = Self {
+            cyclic: path.is_cyclic(),
+            ids: nodes.iter().map(|n| n.id).collect(),
+            positions: nodes.iter().map(|n| n.position).collect(),
+            index_of: nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect(),
+            longest_edge: 0.0,
+        };
+        shape.longest_edge = (0..shape.edge_count())
+            .map(|i| shape.positions[i].distance(&shape.positions[shape.next(i)]))
+            .fold(0.0, f64::max);
+        shape
+    }
+
+    fn edge_count(&self) -> usize {
+        match self.positions.len() {
+            0 | 1 => 0,
+            n if self.cyclic && n > 2 => n,
+            n => n - 1,
+        }
+    }
+
+    fn next(&self, index: usize) -> usize {
+        (index + 1) % self.positions.len()
+    }
+}
+
+/// A copy of every path's polyline that can be looked up by node key. The
+/// spatial index finds nodes near a point, and this turns them into the edges
+/// that end at those nodes.
+pub struct Topology {
+    paths: Vec<PathShape>,
+    slack: f64,
+    torus: Option<Torus>,
+}
+
+impl Topology {
+    /// `slack` is how far the indexed positions may lag behind the ones
+    /// stored here, e.g. because nodes moved since the index was updated.
+    /// On a `torus` edges are looked up through the seams, and `index` must
+    /// wrap its queries too.
+    pub fn new(paths: &[Path], slack: f64, torus: Option<Torus>) -> Self {
+        Self {
+            paths: paths.iter().map(PathShape::new).collect(),
+            slack,
+            torus,
+        }
+    }
+
+    /// Replaces the stored copy of the path at `path_index`.
+    pub fn set_path(&mut self, path_index: usize, path: &Path) {
+        self.paths[path_index] = PathShape::new(path);
+    }
+
+    /// Brings the stored copy of the path at `path_index` up to date with
+    /// the edits recorded while updating it. Moves are applied one by one,
+    /// while a path that gained or lost nodes is copied again, since its
+    /// edges are numbered by position along it.
+    pub fn apply(&mut self, path_index: usize, path: &Path, changes: &[IndexChange]) {
+        let resized = changes
+            .iter()
+            .any(|change| !matches!(change, IndexChange::Move(..)));
+        if resized {
+            self.set_path(path_index, path);
+            return;
+        }
+
+        for change in changes.iter() {
+            if let IndexChange::Move(entry, to) = change {
+                self.set_position(entry.key, Vec2::from_point2(to));
+            }
+        }
+    }
+
+    /// Records that a node moved.
+    pub fn set_position(&mut self, key: NodeKey, position: Vec2) {
+        let shape = &mut self.paths[key.path];
+        if let Some(&index) = shape.index_of.get(&key.node) {
+            shape.positions[index] = position;
+        }
+
+        let longest = self.edges_at(key).map(|e| e.length()).fold(0.0, f64::max);
+        let shape = &mut self.paths[key.path];
+        shape.longest_edge = shape.longest_edge.max(longest);
+    }
+
+    pub fn edge(&self, path: usize, start: usize) -> Edge {
+        let shape = &self.paths[path];
+        let end = shape.next(start);
+        Edge {
+            path,
+            start,
+            from: NodeKey {
+                path,
+                node: shape.ids[start],
+            },
+            to: NodeKey {
+                path,
+                node: shape.ids[end],
+            },
+            a: shape.positions[start],
+            b: shape.positions[end],
+        }
+    }
+
+    /// Every edge of the path at `path_index`.
+    pub fn edges(&self, path_index: usize) -> impl Iterator<Item = Edge> + '_ {
+        (0..self.paths[path_index].edge_count()).map(move |i| self.edge(path_index, i))
+    }
+
+    /// The edges ending at the node with `key`, if it is known.
+    pub fn edges_at(&self, key: NodeKey) -> impl Iterator<Item = Edge> + '_ {
+        self.edge_starts(key)
+            .map(move |(path, start)| self.edge(path, start))
+    }
+
+    /// Path and start index of the edges ending at the node with `key`.
+    fn edge_starts(&self, key: NodeKey) -> impl Iterator<Item = (usize, usize)> {
+        let shape = &self.paths[key.path];
+        let count = shape.edge_count();
+        let (outgoing, incoming) = match shape.index_of.get(&key.node) {
+            Some(&index) if count > 0 => {
+                let outgoing = Some(index).filter(|i| *i < count);
+                let incoming = match index {
+                    0 if count == shape.positions.len() => Some(count - 1),
+                    0 => None,
+                    i => Some(i - 1),
+                };
+                (outgoing, incoming)
+            }
+            _ => (None, None),
+        };
+
+        outgoing
+            .into_iter()
+            .chain(incoming)
+            .map(move |start| (key.path, start))
+    }
+
+    /// The longest edge of any path, which bounds how far from an indexed
+    /// node an edge touching it can reach.
+    pub fn longest_edge(&self) -> f64 {
+        self.paths
+            .iter()
+            .map(|p| p.longest_edge)
+            .fold(0.0, f64::max)
+    }
+
+    /// At least every edge passing within `radius` of `position`, ordered by
+    /// path and position along it. On a torus each edge is moved to the copy
+    /// whose start is closest to `position`.
+    pub fn edges_near(&self, index: &dyn SpatialIndex, position: &Vec2, radius: f64) -> Vec<Edge> {
+        let search_radius = radius + self.longest_edge() + self.slack;
+        let mut starts: Vec<(usize, usize)> = index
+            .locate_within_distance(position, search_radius)
+            .into_iter()
+            .flat_map(|n| self.edge_starts(n.key))
+            .collect();
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+            .into_iter()
+            .map(|(path, start)| {
+                let mut edge = self.edge(path, start);
+                if let Some(torus) = &self.torus {
+                    let shift = torus.nearest_image(edge.a, *position) - edge.a;
+                    edge.a += shift;
+                    edge.b += shift;
+                }
+                edge
+            })
+            .collect()
+    }
+
+    /// The first edge that the segment `a`-`b` crosses, skipping edges that
+    /// end at one of the `ignored` nodes.
+    pub fn find_crossing(
+        &self,
+        index: &dyn SpatialIndex,
+        a: Vec2,
+        b: Vec2,
+        ignored: &[NodeKey],
+    ) -> Option<(Edge, Vec2)> {
+        let center = (a + b) / 2.0;
+        let edges = self.edges_near(index, &center, a.distance(&b) / 2.0);
+        first_crossing(&edges, a, b, ignored)
+    }
+
+    /// Every pair of crossing edges, within and across paths, each reported
+    /// once with the lower edge first. `index` must hold the stored positions.
+    /// On a torus the crossing points are folded into the view.
+    pub fn crossings(&self, index: &dyn SpatialIndex) -> Vec<Crossing> {
+        let mut crossings = vec![];
+        for path in 0..self.paths.len() {
+            for edge in self.edges(path) {
+                let center = (edge.a + edge.b) / 2.0;
+                for other in self.edges_near(index, &center, edge.length() / 2.0) {
+                    if other.id() <= edge.id() {
+                        continue;
+                    }
+                    if let Some(point) = segment_intersection(edge.a, edge.b, other.a, other.b) {
+                        crossings.push(Crossing {
+                            first: edge.id(),
+                            second: other.id(),
+                            point: self.torus.map_or(point, |torus| torus.wrap(point)),
+                        });
+                    }
+                }
+            }
+        }
+        crossings
+    }
+}
+
+/// The first of `edges` that the segment `a`-`b` crosses, skipping edges that
+/// end at one of the `ignored` nodes.
+pub fn first_crossing(
+    edges: &[Edge],
+    a: Vec2,
+    b: Vec2,
+    ignored: &[NodeKey],
+) -> Option<(Edge, Vec2)> {
+    edges
+        .iter()
+        .filter(|e| !ignored.iter().any(|key| e.touches(*key)))
+        .find_map(|e| segment_intersection(a, b, e.a, e.b).map(|point| (*e, point)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Settings;
+    use crate::node::Node;
+    use crate::spatial_index::*;
+    use crate::topology::*;
+    use crate::torus::TorusIndex;
+
+    fn path(points: &[(f64, f64)], cyclic: bool) -> Path {
+        let nodes = points
+            .iter()
+            .map(|(x, y)| Node::new_with_position(Vec2::new(*x, *y)))
+            .collect();
+        Path::new(nodes, cyclic)
+    }
+
+    fn index(paths: &[Path]) -> Box<dyn SpatialIndex> {
+        let entries = paths
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| p.index_entries(i))
+            .collect();
+        index_entries(entries, SpatialIndexType::RTree, &Settings::new(100, 100))
+    }
+
+    #[test]
+    fn topology_edges_at() {
+        let paths = vec![
+            path(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)], true),
+            path(&[(5.0, 0.0), (6.0, 0.0)], false),
+        ];
+        let topology = Topology::new(&paths, 0.0, None);
+
+        let starts = |key| -> Vec<usize> { topology.edges_at(key).map(|e| e.start).collect() };
+        assert_eq!(starts(NodeKey { path: 0, node: 0 }), vec![0, 2]);
+        assert_eq!(starts(NodeKey { path: 1, node: 0 }), vec![0]);
+        assert_eq!(starts(NodeKey { path: 1, node: 1 }), vec![0]);
+        assert_eq!(topology.edges(0).count(), 3);
+    }
+
+    #[test]
+    fn topology_apply() {
+        let paths = vec![path(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)], false)];
+        let mut topology = Topology::new(&paths, 0.0, None);
+
+        let mut changes = IndexChanges::new();
+        changes.move_node(1, Vec2::new(10.0, 0.0), Vec2::new(10.0, 5.0));
+        topology.apply(0, &paths[0], changes.changes());
+        assert_eq!(topology.edge(0, 1).a, Vec2::new(10.0, 5.0));
+        assert_eq!(topology.edges(0).count(), 2);
+
+        let grown = path(&[(0.0, 0.0), (5.0, 0.0), (10.0, 5.0), (20.0, 0.0)], false);
+        changes.clear();
+        changes.insert(3, Vec2::new(5.0, 0.0));
+        topology.apply(0, &grown, changes.changes());
+        assert_eq!(topology.edges(0).count(), 3);
+        assert_eq!(topology.edge(0, 1).b, Vec2::new(10.0, 5.0));
+    }
+
+    #[test]
+    fn topology_find_crossing() {
+        let paths = vec![
+            path(&[(0.0, 0.0), (10.0, 0.0)], false),
+            path(&[(5.0, 5.0), (5.0, 1.0)], false),
+        ];
+        let index = index(&paths);
+        let mut topology = Topology::new(&paths, 0.0, None);
+
+        let tip = NodeKey { path: 1, node: 1 };
+        let crossing = topology.find_crossing(
+            index.as_ref(),
+            Vec2::new(5.0, 5.0),
+            Vec2::new(5.0, -1.0),
+            &[tip],
+        );
+        assert_eq!(
+            crossing.map(|(e, p)| (e.path, p)),
+            Some((0, Vec2::new(5.0, 0.0)))
+        );
+
+        topology.set_position(tip, Vec2::new(5.0, 0.5));
+        let safe = topology.find_crossing(
+            index.as_ref(),
+            Vec2::new(5.0, 5.0),
+            Vec2::new(5.0, 0.5),
+            &[tip],
+        );
+        assert_eq!(safe, None);
+    }
+
+    #[test]
+    fn topology_crossings_through_seam() {
+        // The first line straddles the seam at x = 100 and meets the copy of
+        // the second one at x = 105.
+        let paths = vec![
+            path(&[(90.0, 50.0), (110.0, 50.0)], false),
+            path(&[(5.0, 40.0), (5.0, 60.0)], false),
+        ];
+        let torus = Torus::new(100.0, 100.0);
+        let mut index = TorusIndex::new(Box::new(RTreeIndex::new()), torus);
+        index.index(
+            paths
+                .iter()
+                .enumerate()
+                .flat_map(|(i, p)| p.index_entries(i))
+                .collect(),
+        );
+
+        let flat = Topology::new(&paths, 0.0, None);
+        assert!(flat.crossings(&index).is_empty());
+
+        let topology = Topology::new(&paths, 0.0, Some(torus));
+        assert_eq!(
+            topology.crossings(&index),
+            vec![Crossing {
+                first: EdgeRef { path: 0, edge: 0 },
+                second: EdgeRef { path: 1, edge: 0 },
+                point: Vec2::new(5.0, 50.0),
+            }]
+        );
+
+        let crossing =
+            topology.find_crossing(&index, Vec2::new(95.0, 45.0), Vec2::new(95.0, 55.0), &[]);
+        assert_eq!(
+            crossing.map(|(e, p)| (e.path, p)),
+            Some((0, Vec2::new(95.0, 50.0)))
+        );
+        let crossing =
+            topology.find_crossing(&index, Vec2::new(3.0, 45.0), Vec2::new(3.0, 55.0), &[]);
+        assert_eq!(
+            crossing.map(|(e, p)| (e.path, p)),
+            Some((0, Vec2::new(3.0, 50.0)))
+        );
+    }
+
+    #[test]
+    fn topology_crossings() {
+        let paths = vec![
+            // A bow tie crosses itself once.
+            path(&[(0.0, 0.0), (4.0, 4.0), (4.0, 0.0), (0.0, 4.0)], true),
+            path(&[(-1.0, 1.0), (1.0, 1.0)], false),
+        ];
+        let index = index(&paths);
+        let topology = Topology::new(&paths, 0.0, None);
+
+        let crossings = topology.crossings(index.as_ref());
+        assert_eq!(
+            crossings,
+            vec![
+                Crossing {
+                    first: EdgeRef { path: 0, edge: 0 },
+                    second: EdgeRef { path: 0, edge: 2 },
+                    point: Vec2::new(2.0, 2.0),
+                },
+                Crossing {
+                    first: EdgeRef { path: 0, edge: 3 },
+                    second: EdgeRef { path: 1, edge: 0 },
+                    point: Vec2::new(0.0, 1.0),
+                },
+            ]
+        );
+    }
+}
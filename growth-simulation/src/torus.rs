@@ -0,0 +1,401 @@
+use crate::spatial_index::*;
+use crate::vec2::{Point2, Vec2};
+
+/// The `width`×`height` view with its opposite edges glued together.
+///
+/// Node positions are never wrapped: paths keep moving through continuous
+/// coordinates so that neighboring nodes stay close to each other, and only
+/// the spatial index and rendering fold positions back into the view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Torus {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Torus {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self { width, height }
+    }
+
+    /// The copy of `point` that lies inside the view.
+    pub fn wrap(&self, point: Vec2) -> Vec2 {
+        Vec2::new(
+            point.x.rem_euclid(self.width),
+            point.y.rem_euclid(self.height),
+        )
+    }
+
+    /// The shortest offset from `from` to any copy of `to`.
+    pub fn offset(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let wrap = |d: f64, period: f64| d - period * (d / period).round();
+        let diff = to - from;
+        Vec2::new(wrap(diff.x, self.width), wrap(diff.y, self.height))
+    }
+
+    /// The copy of `point` that is closest to `near`.
+    pub fn nearest_image(&self, point: Vec2, near: Vec2) -> Vec2 {
+        near + self.offset(near, point)
+    }
+
+    /// Splits a polyline into pieces that each lie inside the view, cutting
+    /// segments where they cross an edge. Each piece comes with whether it is
+    /// closed, which only happens for a cyclic polyline that never crosses.
+    pub fn split_polyline(&self, points: &[Vec2], cyclic: bool) -> Vec<(Vec<Vec2>, bool)> {
+        if points.len() < 2 {
+            return vec![(points.iter().map(|p| self.wrap(*p)).collect(), false)];
+        }
+
+        let cell = |p: Vec2| {
+            (
+                (p.x / self.width).floor() as i64,
+                (p.y / self.height).floor() as i64,
+            )
+        };
+        let segments = if cyclic {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+
+        let mut pieces = vec![];
+        let mut current: Vec<Vec2> = vec![];
+        let mut current_cell = None;
+
+        for i in 0..segments {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+
+            let mut cuts = vec![0.0, 1.0];
+            seam_crossings(a.x, b.x, self.width, &mut cuts);
+            seam_crossings(a.y, b.y, self.height, &mut cuts);
+            cuts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            cuts.dedup();
+
+            for t in cuts.windows(2) {
+                let from = a + (b - a) * t[0];
+                let to = a + (b - a) * t[1];
+                let piece_cell = cell((from + to) / 2.0);
+                let shift = Vec2::new(
+                    piece_cell.0 as f64 * self.width,
+                    piece_cell.1 as f64 * self.height,
+                );
+                // Cut points can land a rounding error outside the view.
+                let local = |p: Vec2| {
+                    Vec2::new(
+                        (p.x - shift.x).clamp(0.0, self.width),
+                        (p.y - shift.y).clamp(0.0, self.height),
+                    )
+                };
+
+                if current_cell != Some(piece_cell) {
+                    if current.len() > 1 {
+                        pieces.push(std::mem::take(&mut current));
+                    }
+                    current = vec![local(from)];
+                    current_cell = Some(piece_cell);
+                }
+                current.push(local(to));
+            }
+        }
+
+        if pieces.is_empty() && cyclic {
+            current.pop();
+            return vec![(current, true)];
+        }
+
+        if current.len() > 1 {
+            pieces.push(current);
+        }
+        // The last piece of a cyclic polyline runs into the first one.
+        if cyclic && pieces.len() > 1 {
+            let first = pieces.remove(0);
+            let last = pieces.last_mut().unwrap();
+            if last[last.len() - 1].distance(&first[0]) < 1e-9 {
+                last.extend(first.into_iter().skip(1));
+            } else {
+                pieces.insert(0, first);
+            }
+        }
+
+        pieces.into_iter().map(|piece| (piece, false)).collect()
+    }
+}
+
+/// Adds the parameters at which the segment from `a` to `b` crosses a
+/// multiple of `period`.
+fn seam_crossings(a: f64, b: f64, period: f64, cuts: &mut Vec<f64>) {
+    if a == b {
+        return;
+    }
+
+    let (low, high) = (a.min(b), a.max(b));
+    let mut seam = (low / period).floor() + 1.0;
+    while seam * period < high {
+        cuts.push((seam * period - a) / (b - a));
+        seam += 1.0;
+    }
+}
+
+/// Wraps another index so that queries see across the edges of a torus.
+/// Entries are stored folded into the view, and query results come back at
+/// the copy closest to the query position, so offsets to them can be used
+/// directly. Query radii should stay below half the view size.
+pub struct TorusIndex {
+    inner: Box<dyn SpatialIndex>,
+    torus: Torus,
+}
+
+impl TorusIndex {
+    pub fn new(inner: Box<dyn SpatialIndex>, torus: Torus) -> Self {
+        Self { inner, torus }
+    }
+
+    fn wrap_point(&self, point: &Point2) -> Point2 {
+        self.torus.wrap(Vec2::from_point2(point)).as_point2()
+    }
+
+    fn wrap_entry(&self, entry: &IndexEntry) -> IndexEntry {
+        IndexEntry::new(entry.key, self.wrap_point(&entry.position))
+    }
+
+    /// Offsets of the copies of a query around `position` (inside the view)
+    /// that reach into the view.
+    fn images(&self, position: Vec2, radius: f64) -> Vec<Vec2> {
+        let shifts = |p: f64, period: f64| {
+            let mut shifts = vec![0.0];
+            if p + radius >= period {
+                shifts.push(-period);
+            }
+            if p - radius <= 0.0 {
+                shifts.push(period);
+            }
+            shifts
+        };
+
+        let mut images = vec![];
+        for x in shifts(position.x, self.torus.width) {
+            for y in shifts(position.y, self.torus.height) {
+                images.push(Vec2::new(x, y));
+            }
+        }
+        images
+    }
+}
+
+impl SpatialIndex for TorusIndex {
+    fn index(&mut self, entries: Vec<IndexEntry>) {
+        let entries = entries.iter().map(|e| self.wrap_entry(e)).collect();
+        self.inner.index(entries);
+    }
+
+    fn insert(&mut self, entry: IndexEntry) {
+        let entry = self.wrap_entry(&entry);
+        self.inner.insert(entry);
+    }
+
+    fn remove(&mut self, entry: &IndexEntry) {
+        let entry = self.wrap_entry(entry);
+        self.inner.remove(&entry);
+    }
+
+    fn move_entry(&mut self, entry: &IndexEntry, to: Point2) {
+        let (entry, to) = (self.wrap_entry(entry), self.wrap_point(&to));
+        self.inner.move_entry(&entry, to);
+    }
+
+    fn apply(&mut self, changes: &[IndexChange]) {
+        let changes: Vec<IndexChange> = changes
+            .iter()
+            .map(|change| match change {
+                IndexChange::Insert(entry) => IndexChange::Insert(self.wrap_entry(entry)),
+                IndexChange::Remove(entry) => IndexChange::Remove(self.wrap_entry(entry)),
+                IndexChange::Move(entry, to) => {
+                    IndexChange::Move(self.wrap_entry(entry), self.wrap_point(to))
+                }
+            })
+            .collect();
+        self.inner.apply(&changes);
+    }
+
+    fn prefers_rebuild(&self, changes: usize) -> bool {
+        self.inner.prefers_rebuild(changes)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Positions are returned as stored, folded into the view.
+    fn get_neighbors(&self, position: &Vec2, radius: f64) -> Vec<&Point2> {
+        let wrapped = self.torus.wrap(*position);
+        self.images(wrapped, radius)
+            .into_iter()
+            .flat_map(|shift| self.inner.get_neighbors(&(wrapped + shift), radius))
+            .collect()
+    }
+
+    fn locate_within_distance(&self, position: &Vec2, radius: f64) -> Vec<Neighbor> {
+        let wrapped = self.torus.wrap(*position);
+        let mut neighbors: Vec<Neighbor> = self
+            .images(wrapped, radius)
+            .into_iter()
+            .flat_map(|shift| {
+                self.inner
+                    .locate_within_distance(&(wrapped + shift), radius)
+            })
+            .map(|neighbor| {
+                let image = self
+                    .torus
+                    .nearest_image(Vec2::from_point2(&neighbor.position), *position);
+                Neighbor {
+                    key: neighbor.key,
+                    position: image.as_point2(),
+                    distance: position.distance(&image),
+                }
+            })
+            .collect();
+
+        // A large radius can reach the same entry through several copies,
+        // which all map to the same nearest image.
+        neighbors.sort_by_key(|n| n.key);
+        neighbors.dedup_by_key(|n| n.key);
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Settings;
+    use crate::torus::*;
+
+    fn entry(node: u32, x: f64, y: f64) -> IndexEntry {
+        IndexEntry::new(NodeKey { path: 0, node }, Vec2::new(x, y).as_point2())
+    }
+
+    #[test]
+    fn torus_offset() {
+        let torus = Torus::new(100.0, 50.0);
+        assert_eq!(torus.wrap(Vec2::new(-10.0, 120.0)), Vec2::new(90.0, 20.0));
+        assert_eq!(
+            torus.offset(Vec2::new(95.0, 5.0), Vec2::new(5.0, 45.0)),
+            Vec2::new(10.0, -10.0)
+        );
+        assert_eq!(
+            torus.nearest_image(Vec2::new(5.0, 25.0), Vec2::new(295.0, 25.0)),
+            Vec2::new(305.0, 25.0)
+        );
+    }
+
+    #[test]
+    fn torus_split_polyline() {
+        let torus = Torus::new(10.0, 10.0);
+
+        let inside = [
+            Vec2::new(1.0, 1.0),
+            Vec2::new(5.0, 1.0),
+            Vec2::new(5.0, 5.0),
+        ];
+        assert_eq!(
+            torus.split_polyline(&inside, true),
+            vec![(inside.to_vec(), true)]
+        );
+
+        let across = [
+            Vec2::new(6.0, 5.0),
+            Vec2::new(14.0, 5.0),
+            Vec2::new(16.0, 5.0),
+        ];
+        assert_eq!(
+            torus.split_polyline(&across, false),
+            vec![
+                (vec![Vec2::new(6.0, 5.0), Vec2::new(10.0, 5.0)], false),
+                (
+                    vec![
+                        Vec2::new(0.0, 5.0),
+                        Vec2::new(4.0, 5.0),
+                        Vec2::new(6.0, 5.0)
+                    ],
+                    false
+                ),
+            ]
+        );
+
+        // A loop around the seam starts and ends in the same piece.
+        let around = [
+            Vec2::new(-2.0, 5.0),
+            Vec2::new(2.0, 5.0),
+            Vec2::new(2.0, 7.0),
+            Vec2::new(-2.0, 7.0),
+        ];
+        let pieces = torus.split_polyline(&around, true);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[1].0,
+            vec![
+                Vec2::new(10.0, 7.0),
+                Vec2::new(8.0, 7.0),
+                Vec2::new(8.0, 5.0),
+                Vec2::new(10.0, 5.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn torus_split_polyline_stays_in_view() {
+        let torus = Torus::new(10.0, 10.0);
+        let inside = |p: &Vec2| (0.0..=10.0).contains(&p.x) && (0.0..=10.0).contains(&p.y);
+
+        // Diagonals through a corner and across both edges, where the cut
+        // points only meet the seams up to a rounding error.
+        for i in 0..100 {
+            let t = i as f64 * 0.137;
+            let segments = [
+                [Vec2::new(9.3 + t, 8.1 + t), Vec2::new(10.7 + t, 11.9 + t)],
+                [Vec2::new(-0.3 - t, 0.7 + t), Vec2::new(0.1 + t, -0.9 - t)],
+            ];
+            for points in segments.iter() {
+                for (piece, _) in torus.split_polyline(points, false) {
+                    assert!(piece.iter().all(inside), "{:?}", piece);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn torus_index_wraps_queries() {
+        let torus = Torus::new(100.0, 100.0);
+        let settings = Settings::new(100, 100);
+        for index_type in [SpatialIndexType::RTree, SpatialIndexType::Grid] {
+            let mut index = TorusIndex::new(get_spatial_index(index_type, &settings), torus);
+            index.index(vec![
+                entry(0, 98.0, 50.0),
+                entry(1, 50.0, 50.0),
+                entry(2, 1.0, 99.0),
+            ]);
+
+            // Queried from past the left edge, in unwrapped coordinates.
+            let neighbors = index.locate_within_distance(&Vec2::new(-1.0, 52.0), 5.0);
+            assert_eq!(neighbors.len(), 1);
+            assert_eq!(neighbors[0].key.node, 0);
+            assert_eq!(
+                Vec2::from_point2(&neighbors[0].position),
+                Vec2::new(-2.0, 50.0)
+            );
+
+            // Across a corner.
+            let corner = index.locate_within_distance(&Vec2::new(99.0, 1.0), 3.0);
+            assert_eq!(corner.len(), 1);
+            assert_eq!(
+                Vec2::from_point2(&corner[0].position),
+                Vec2::new(101.0, -1.0)
+            );
+
+            index.move_entry(&entry(1, 50.0, 50.0), Vec2::new(103.0, 50.0).as_point2());
+            let moved = index.locate_within_distance(&Vec2::new(1.0, 50.0), 3.0);
+            assert_eq!(
+                moved.iter().map(|n| n.key.node).collect::<Vec<_>>(),
+                vec![0, 1]
+            );
+        }
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(feature = "web")]
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
     // `set_panic_hook` function at least once during initialization, and then
@@ -8,3 +9,40 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+// Logs to the browser console. Calling into JS panics on native targets, so
+// this is a no-op there.
+#[cfg(feature = "web")]
+macro_rules! log {
+    ($($t:tt)*) => {
+        #[cfg(target_arch = "wasm32")]
+        web_sys::console::log_1(&format!($($t)*).into());
+    };
+}
+
+#[cfg(feature = "web")]
+pub(crate) use log;
+
+/// Formats a coordinate with at most three decimals and no trailing zeros.
+pub fn format_coord(val: f64) -> String {
+    let formatted = format!("{:.3}", val);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "" | "-" | "-0" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::format_coord;
+
+    #[test]
+    fn utils_format_coord() {
+        assert_eq!(format_coord(1.0), "1");
+        assert_eq!(format_coord(1.5), "1.5");
+        assert_eq!(format_coord(2.0004), "2");
+        assert_eq!(format_coord(-0.0001), "0");
+        assert_eq!(format_coord(-3.125), "-3.125");
+    }
+}
@@ -167,6 +167,15 @@ impl Vec2 {
         let diff = *self - *other;
         diff.length()
     }
+
+    pub fn dot(&self, other: &Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The z component of the 3D cross product.
+    pub fn cross(&self, other: &Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
 }
 
 #[cfg(test)]
@@ -178,8 +187,8 @@ mod tests {
         let v = Vec2::new(1.5, 2.0);
         assert_eq!(v.x, 1.5);
         assert_eq!(v.y, 2.0);
-        let u = Vec2::new(3.14, 2.6);
-        assert_eq!(u.x, 3.14);
+        let u = Vec2::new(3.25, 2.6);
+        assert_eq!(u.x, 3.25);
         assert_eq!(u.y, 2.6);
     }
 
@@ -321,4 +330,12 @@ mod tests {
         let distance = v.distance(&u);
         assert!(distance > 3.16227 && distance < 3.16228);
     }
+
+    #[test]
+    fn vec2_dot_cross() {
+        let v = Vec2::new(1.0, 2.0);
+        let u = Vec2::new(3.0, -1.0);
+        assert_eq!(v.dot(&u), 1.0);
+        assert_eq!(v.cross(&u), -7.0);
+    }
 }
@@ -0,0 +1,290 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::config::*;
+use crate::density::DensityMap;
+use crate::draw::draw_marker;
+use crate::export::{ExportOptions, CROSSING_COLOR, FIELD_COLOR};
+use crate::field::ForceField;
+use crate::path::Path;
+use crate::simulation::Simulation;
+use crate::svg_path::parse_path_data;
+use crate::utils;
+use crate::utils::log;
+
+#[wasm_bindgen]
+pub struct GrowthSimulation {
+    ctx: Option<web_sys::CanvasRenderingContext2d>,
+    simulation: Simulation,
+}
+
+#[wasm_bindgen]
+impl GrowthSimulation {
+    pub fn new(width: u32, height: u32) -> Self {
+        log!("creating growth simulation");
+        utils::set_panic_hook();
+        Self {
+            ctx: None,
+            simulation: Simulation::new(Config::new(width, height)),
+        }
+    }
+
+    pub fn set_canvas(&mut self, id: String) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let app_container = document
+            .query_selector("my-app")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::HtmlElement>()
+            .unwrap();
+
+        let selector = format!("#{}", id);
+        log!("selecting {}", selector);
+        let canvas: web_sys::HtmlCanvasElement = app_container
+            .shadow_root()
+            .unwrap()
+            .query_selector(selector.as_str())
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .map_err(|_| ())
+            .unwrap();
+
+        let context = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .unwrap();
+
+        self.ctx = Some(context);
+    }
+
+    pub fn add_path(&mut self, path: Path) {
+        self.simulation.add_path(path);
+    }
+
+    pub fn get_config(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self.simulation.config()).unwrap()
+    }
+
+    pub fn update_config(&mut self, val: &JsValue) {
+        log!("updating config");
+        let config: Config = serde_wasm_bindgen::from_value(val.clone()).unwrap();
+        *self.simulation.config_mut() = config;
+    }
+
+    pub fn update_settings(&mut self, val: &JsValue) {
+        log!("updating settings");
+        let new_settings: Settings = serde_wasm_bindgen::from_value(val.clone()).unwrap();
+        self.simulation.config_mut().settings = new_settings;
+    }
+
+    pub fn update_initialization(&mut self, val: &JsValue) {
+        log!("updating initialization");
+        let init: InitializationConfig = serde_wasm_bindgen::from_value(val.clone()).unwrap();
+        self.simulation.config_mut().initialization = init;
+    }
+
+    pub fn update_recording(&mut self, val: &JsValue) {
+        log!("updating recording");
+        let rec: RecordingConfig = serde_wasm_bindgen::from_value(val.clone()).unwrap();
+        self.simulation.config_mut().recording = rec;
+    }
+
+    /// Checks SVG path data before it goes into the config, so it doesn't
+    /// only show up as a failed `setup`.
+    pub fn check_svg_path(path_data: &str) -> Result<(), JsValue> {
+        parse_path_data(path_data, f64::INFINITY)
+            .map(|_| ())
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Throws if SVG path data in the config doesn't parse, leaving the
+    /// simulation as it was.
+    pub fn setup(&mut self) -> Result<(), JsValue> {
+        log!("SETUP");
+        self.simulation
+            .setup()
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    pub fn reset(&mut self) {
+        self.simulation.reset();
+    }
+
+    pub fn draw(&self) {
+        let ctx = self.ctx.as_ref().unwrap();
+        let settings = self.simulation.config().settings;
+
+        ctx.save();
+
+        ctx.clear_rect(0.0, 0.0, settings.width as f64, settings.height as f64);
+
+        ctx.set_fill_style_str("#000000");
+
+        ctx.fill_rect(0.0, 0.0, settings.width as f64, settings.height as f64);
+
+        for line in self.simulation.styled_paths() {
+            line.draw(ctx);
+        }
+
+        if self.simulation.config().bounds.draw {
+            self.simulation.bounds().draw(ctx);
+        }
+
+        ctx.restore();
+    }
+
+    /// Every path as `{ cyclic, nodes }`, with each node's position,
+    /// velocity, `fixed`, `birth`, `generation` and `scalars`.
+    pub fn get_paths(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self.simulation.paths()).unwrap()
+    }
+
+    /// Frames since setup, for turning node births into ages.
+    pub fn frame(&self) -> u64 {
+        self.simulation.frame()
+    }
+
+    /// Sets one of a node's user scalars. Every node has 4 slots, 0 to 3;
+    /// throws naming the path, node or slot that doesn't exist.
+    pub fn set_node_scalar(
+        &mut self,
+        path: usize,
+        node: usize,
+        slot: usize,
+        value: f64,
+    ) -> Result<(), JsValue> {
+        self.simulation
+            .set_node_scalar(path, node, slot, value)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Every pair of crossing edges as
+    /// `{ first: { path, edge }, second: { path, edge }, point: { x, y } }`.
+    pub fn crossings(&mut self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.simulation.crossings()).unwrap()
+    }
+
+    /// Circles every crossing on top of the current frame.
+    pub fn draw_crossings(&mut self) {
+        let crossings = self.simulation.crossings();
+        let ctx = self.ctx.as_ref().unwrap();
+        for crossing in crossings.iter() {
+            draw_marker(ctx, crossing.point, 6.0, CROSSING_COLOR);
+        }
+    }
+
+    /// Adds a force field given as
+    /// `{ kind: 'Attractor' | 'Repeller' | { Flow: { direction: { x, y } } },
+    /// position: { x, y }, strength, radius, falloff? }` and returns its index.
+    pub fn add_field(&mut self, val: &JsValue) -> Result<usize, JsValue> {
+        let field: ForceField = serde_wasm_bindgen::from_value(val.clone())?;
+        Ok(self.simulation.add_field(field))
+    }
+
+    pub fn remove_field(&mut self, index: usize) -> bool {
+        self.simulation.remove_field(index).is_some()
+    }
+
+    pub fn list_fields(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self.simulation.fields()).unwrap()
+    }
+
+    pub fn clear_fields(&mut self) {
+        self.simulation.clear_fields();
+    }
+
+    /// Circles every force field's range on top of the current frame.
+    pub fn draw_fields(&self) {
+        let ctx = self.ctx.as_ref().unwrap();
+        for field in self.simulation.fields() {
+            let radius = if field.radius > 0.0 {
+                field.radius
+            } else {
+                6.0
+            };
+            draw_marker(ctx, field.position, radius, FIELD_COLOR);
+        }
+    }
+
+    pub fn to_svg(&self, include_bounds: bool, include_background: bool) -> String {
+        self.simulation.to_svg(ExportOptions {
+            include_bounds,
+            include_background,
+        })
+    }
+
+    pub fn save_state(&self) -> String {
+        self.simulation.save_state()
+    }
+
+    pub fn load_state(&mut self, json: &str) -> Result<(), JsValue> {
+        self.simulation
+            .load_state(json)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    pub fn save_state_binary(&self) -> Vec<u8> {
+        self.simulation.save_state_binary()
+    }
+
+    pub fn load_state_binary(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.simulation
+            .load_state_binary(bytes)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    /// Loads a PNG whose darkness scales growth across the canvas.
+    pub fn load_density_map(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        let map = DensityMap::from_png(bytes).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.simulation.set_density_map(Some(map));
+        Ok(())
+    }
+
+    pub fn clear_density_map(&mut self) {
+        self.simulation.set_density_map(None);
+    }
+
+    pub fn recorded_frame_count(&self) -> usize {
+        self.simulation.recording().len()
+    }
+
+    /// Recorded frame `index` as a PNG, for saving the frames as a numbered
+    /// sequence.
+    pub fn recorded_frame_png(&self, index: usize) -> Option<Vec<u8>> {
+        self.simulation
+            .recording()
+            .frames()
+            .get(index)
+            .map(|frame| frame.to_png())
+    }
+
+    /// Every recorded frame as an animated GIF, timed by
+    /// `config.recording.frame_delay`.
+    pub fn recording_to_gif(&self) -> Result<Vec<u8>, JsValue> {
+        let frame_delay = self.simulation.config().recording.frame_delay;
+        self.simulation
+            .recording()
+            .to_gif(frame_delay)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    pub fn clear_recording(&mut self) {
+        self.simulation.clear_recording();
+    }
+
+    pub fn to_gcode(&self, plotter_config: PlotterConfig) -> String {
+        self.simulation.to_gcode(&plotter_config)
+    }
+
+    pub fn to_hpgl(&self, plotter_config: PlotterConfig) -> String {
+        self.simulation.to_hpgl(&plotter_config)
+    }
+
+    pub fn update(&mut self) {
+        self.simulation.update();
+        self.draw();
+    }
+}
@@ -0,0 +1,73 @@
+//! Test suite for the `growth-sim` command-line renderer.
+
+#![cfg(feature = "cli")]
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("growth-sim-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn growth_sim() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_growth-sim"))
+}
+
+#[test]
+fn cli_renders_svg_png_and_snapshot() {
+    let dir = temp_dir("render");
+    let config_path = dir.join("config.toml");
+    let output = growth_sim().arg("--print-config").output().unwrap();
+    fs::write(&config_path, &output.stdout).unwrap();
+
+    let status = growth_sim()
+        .arg(&config_path)
+        .args(["--steps", "5", "--output"])
+        .arg(dir.join("out.svg"))
+        .arg("--snapshot")
+        .arg(dir.join("state.bin"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(fs::read_to_string(dir.join("out.svg"))
+        .unwrap()
+        .starts_with("<svg"));
+
+    let status = growth_sim()
+        .arg("--resume")
+        .arg(dir.join("state.bin"))
+        .args(["--steps", "5", "--output"])
+        .arg(dir.join("out.png"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(&fs::read(dir.join("out.png")).unwrap()[1..4], b"PNG");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn cli_rejects_missing_config() {
+    let status = growth_sim().args(["--steps", "1"]).status().unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn cli_rejects_invalid_arguments() {
+    let cases = [
+        (&["config.toml", "--steps", "0"][..], "steps must be"),
+        (
+            &["--resume", "state.bin", "--seed", "3"][..],
+            "cannot be combined",
+        ),
+    ];
+    for (args, message) in cases.iter() {
+        let output = growth_sim().args(*args).output().unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains(message));
+    }
+}
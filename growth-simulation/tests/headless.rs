@@ -0,0 +1,31 @@
+//! Test suite for the native, browser-free simulation core.
+
+use growth_simulation::config::Config;
+use growth_simulation::simulation::Simulation;
+
+fn run(seed: u64, steps: usize) -> Simulation {
+    let mut config = Config::new(400, 400);
+    config.seed = seed;
+    let mut simulation = Simulation::new(config);
+    simulation.setup().unwrap();
+
+    for _ in 0..steps {
+        simulation.update();
+    }
+
+    simulation
+}
+
+#[test]
+fn headless_simulation_runs() {
+    let simulation = run(0, 100);
+    assert_eq!(simulation.paths().len(), 1);
+    assert!(simulation.node_count() > 0);
+}
+
+#[test]
+fn headless_simulation_is_reproducible() {
+    let a = run(3, 100);
+    let b = run(3, 100);
+    assert_eq!(a.paths(), b.paths());
+}
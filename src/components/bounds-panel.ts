@@ -1,11 +1,12 @@
 import { css, html, LitElement } from 'lit';
 import { customElement, property } from 'lit/decorators.js';
 
-import { Bounds } from '../growth-simulation/config';
+import { Bounds, BoundsShape, Point, SvgConfig } from '../growth-simulation/config';
 
 import './range-slider';
+import './svg-path-input';
 
-export const BOUNDS_TYPES = ['None', 'View', 'Rect', 'Circle'] as const;
+export const BOUNDS_TYPES = ['None', 'View', 'Rect', 'Circle', 'Polygon', 'Svg', 'Composite', 'Torus'] as const;
 
 export type BoundsType = typeof BOUNDS_TYPES[number];
 
@@ -22,6 +23,22 @@ export const rectConfig = {
     },
 };
 
+// One "x, y" vertex per line. A blank line starts a hole.
+const parseRings = (text: string): Point[][] =>
+    text
+        .split(/\n\s*\n/)
+        .map((ring) =>
+            ring
+                .split('\n')
+                .map((line) => line.split(/[\s,]+/).filter((v) => v !== '').map(Number))
+                .filter((v) => v.length === 2 && v.every((n) => !isNaN(n)))
+                .map(([x, y]) => ({ x, y }))
+        )
+        .filter((ring) => ring.length > 2);
+
+const formatRings = (rings: Point[][]): string =>
+    rings.map((ring) => ring.map((p) => `${p.x}, ${p.y}`).join('\n')).join('\n\n');
+
 export const circleConfig = {
     radius: {
         label: 'Radius',
@@ -62,6 +79,12 @@ export class BoundsPanel extends LitElement {
             display: block;
             margin-bottom: 16px;
         }
+
+        .vertices {
+            width: 100%;
+            min-height: 160px;
+            font-family: monospace;
+        }
     `;
 
     private updateSettings(newSettings: Partial<Bounds>) {
@@ -98,6 +121,26 @@ export class BoundsPanel extends LitElement {
         });
     }
 
+    private onPolygonChange(event: Event) {
+        const [vertices = [], ...holes] = parseRings((event.target as HTMLTextAreaElement).value);
+        this.updateSettings({
+            polygon_config: { vertices, holes },
+        });
+    }
+
+    private onSvgChange(event: CustomEvent) {
+        this.updateSettings({ svg_config: event.detail.value as SvgConfig });
+    }
+
+    private onCompositeChange(event: Event) {
+        try {
+            const composite = JSON.parse((event.target as HTMLTextAreaElement).value);
+            this.updateSettings({ composite: composite as BoundsShape });
+        } catch (err) {
+            console.log('invalid composite bounds', err);
+        }
+    }
+
     private toggleDrawBounds(event: Event) {
         this.updateSettings({
             draw: !this.settings?.draw,
@@ -141,6 +184,38 @@ export class BoundsPanel extends LitElement {
                     @change=${(event: CustomEvent) => this.onCircleSettingChange('radius', event)}
                 ></range-slider>
             `;
+        } else if (boundsType === 'Polygon') {
+            const polygon = this.settings?.polygon_config;
+            const rings = polygon ? [polygon.vertices, ...polygon.holes] : [];
+            content = html`
+                <label>
+                    Vertices (one "x, y" per line, blank line starts a hole)
+                    <textarea
+                        class="vertices"
+                        .value=${formatRings(rings)}
+                        @change=${this.onPolygonChange}
+                    ></textarea>
+                </label>
+            `;
+        } else if (boundsType === 'Svg') {
+            content = html`
+                <svg-path-input
+                    .settings=${this.settings?.svg_config}
+                    .maxSize=${Math.max(this.width, this.height)}
+                    @change=${this.onSvgChange}
+                ></svg-path-input>
+            `;
+        } else if (boundsType === 'Composite') {
+            content = html`
+                <label>
+                    Shape (JSON, e.g. {"Difference": [{"Circle": {"radius": 150}}, {"Circle": {"radius": 75}}]})
+                    <textarea
+                        class="vertices"
+                        .value=${JSON.stringify(this.settings?.composite ?? 'View', null, 2)}
+                        @change=${this.onCompositeChange}
+                    ></textarea>
+                </label>
+            `;
         }
 
         return html`
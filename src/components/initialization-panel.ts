@@ -1,12 +1,13 @@
 import { css, html, LitElement } from 'lit';
 import { customElement, property } from 'lit/decorators.js';
 
-import { Initialization } from '../growth-simulation/config';
+import { Initialization, SvgConfig } from '../growth-simulation/config';
 
 import './button-element';
 import './range-slider';
+import './svg-path-input';
 
-export const INITIALIZATION_TYPES = ['HorizontalLine', 'VerticalLine', 'Polygon'] as const;
+export const INITIALIZATION_TYPES = ['HorizontalLine', 'VerticalLine', 'Polygon', 'Svg'] as const;
 
 export type InitializationType = typeof INITIALIZATION_TYPES[number];
 
@@ -74,6 +75,10 @@ export class InitializationPanel extends LitElement {
         });
     }
 
+    private onSvgChange(event: CustomEvent) {
+        this.updateSettings({ svg_config: event.detail.value as SvgConfig });
+    }
+
     render() {
         console.log('initialization', this.settings);
         const initType = this.settings?.init_type;
@@ -100,6 +105,13 @@ export class InitializationPanel extends LitElement {
                         : html``;
                 })}
             `;
+        } else if (initType === 'Svg') {
+            content = html`
+                <svg-path-input
+                    .settings=${this.settings?.svg_config}
+                    @change=${this.onSvgChange}
+                ></svg-path-input>
+            `;
         }
 
         return html`
@@ -79,6 +79,85 @@ const settingsConfig = {
         max: 1,
         step: 0.01,
     },
+    injection_count: {
+        label: 'Injections per Frame',
+        min: 1,
+        max: 20,
+        step: 1,
+    },
+    max_neighbors: {
+        label: 'Max Neighbors',
+        min: 0,
+        max: 50,
+        step: 1,
+    },
+    excluded_neighbors: {
+        label: 'Excluded Path Neighbors',
+        min: 0,
+        max: 10,
+        step: 1,
+    },
+    wall_force: {
+        label: 'Wall Force',
+        min: 0,
+        max: 5,
+        step: 0.01,
+    },
+    wall_distance: {
+        label: 'Wall Distance',
+        min: 0,
+        max: 100,
+        step: 1,
+    },
+    noise_strength: {
+        label: 'Noise Strength',
+        min: 0,
+        max: 2,
+        step: 0.01,
+    },
+    noise_scale: {
+        label: 'Noise Scale',
+        min: 0.001,
+        max: 0.1,
+        step: 0.001,
+    },
+    noise_octaves: {
+        label: 'Noise Octaves',
+        min: 1,
+        max: 8,
+        step: 1,
+    },
+    noise_speed: {
+        label: 'Noise Speed',
+        min: 0,
+        max: 0.1,
+        step: 0.001,
+    },
+    noise_seed: {
+        label: 'Noise Seed',
+        min: 0,
+        max: 1000,
+        step: 1,
+    },
+};
+
+const modeConfig = {
+    separation_mode: {
+        label: 'Separation Mode',
+        options: ['Nodes', 'Edges'],
+    },
+    injection_strategy: {
+        label: 'Injection Strategy',
+        options: ['Uniform', 'Curvature', 'Tension', 'Age'],
+    },
+    wall_mode: {
+        label: 'Wall Mode',
+        options: ['Freeze', 'Repel', 'Reflect', 'Clamp'],
+    },
+    noise_type: {
+        label: 'Noise Type',
+        options: ['Curl', 'Perlin'],
+    },
 };
 
 @customElement('settings-panel')
@@ -90,6 +169,11 @@ export class SettingsPanel extends LitElement {
         .controls {
             margin-bottom: 12px;
         }
+
+        .mode {
+            display: block;
+            margin-bottom: 12px;
+        }
     `;
 
     private updateSettings(newSettings: Partial<Settings>) {
@@ -106,6 +190,10 @@ export class SettingsPanel extends LitElement {
         this.updateSettings({ [settingName]: event.detail.value });
     }
 
+    private onModeChange(settingName: keyof typeof modeConfig, event: Event) {
+        this.updateSettings({ [settingName]: (event.target as HTMLSelectElement).value });
+    }
+
     private randomize() {
         const newSettings = { ...this.settings };
 
@@ -121,7 +209,8 @@ export class SettingsPanel extends LitElement {
             }
 
             const range = config.max - config.min;
-            newSettings[key] = Math.random() * range + config.min;
+            const value = Math.random() * range + config.min;
+            newSettings[key] = config.step === 1 ? Math.round(value) : value;
         });
 
         this.updateSettings(newSettings);
@@ -148,6 +237,23 @@ export class SettingsPanel extends LitElement {
                           `
                         : html``;
                 })}
+                ${Object.keys(modeConfig).map((k) => {
+                    const key = k as keyof typeof modeConfig;
+                    const config = modeConfig[key];
+                    return html`
+                        <label class="mode">
+                            ${config.label}
+                            <select @change=${(event: Event) => this.onModeChange(key, event)}>
+                                ${config.options.map(
+                                    (o) =>
+                                        html`<option value=${o} ?selected=${o === this.settings?.[key]}>
+                                            ${o}
+                                        </option>`
+                                )}
+                            </select>
+                        </label>
+                    `;
+                })}
             </div>
             <button-element @click=${this.randomize}>Randomize</button-element>
         `;
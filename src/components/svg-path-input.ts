@@ -0,0 +1,88 @@
+import { css, html, LitElement } from 'lit';
+import { customElement, property, state } from 'lit/decorators.js';
+import { SVGPathData } from 'svg-pathdata';
+
+import { SvgConfig } from '../growth-simulation/config';
+
+import './range-slider';
+
+const sizeConfig = {
+    label: 'Size',
+    min: 0,
+    step: 1,
+};
+
+@customElement('svg-path-input')
+export class SvgPathInput extends LitElement {
+    @property({ type: Object })
+    settings?: SvgConfig;
+
+    @property({ type: Number })
+    maxSize = 400;
+
+    @state()
+    private error = '';
+
+    static styles = css`
+        :host {
+            display: flex;
+            flex-direction: column;
+        }
+
+        textarea {
+            width: 100%;
+            min-height: 120px;
+            margin-bottom: 16px;
+            font-family: monospace;
+        }
+
+        .error {
+            color: #ff3333;
+            margin-bottom: 16px;
+        }
+    `;
+
+    private updateSettings(newSettings: Partial<SvgConfig>) {
+        this.dispatchEvent(
+            new CustomEvent('change', {
+                detail: { value: { ...this.settings, ...newSettings } },
+            })
+        );
+    }
+
+    private onPathDataChange(event: Event) {
+        const value = (event.target as HTMLTextAreaElement).value;
+        try {
+            new SVGPathData(value);
+            this.error = '';
+        } catch (err) {
+            this.error = String(err);
+            return;
+        }
+        this.updateSettings({ path_data: value });
+    }
+
+    render() {
+        return html`
+            <label>
+                SVG path data
+                <textarea
+                    .value=${this.settings?.path_data || ''}
+                    @change=${this.onPathDataChange}
+                ></textarea>
+            </label>
+            ${this.error ? html`<div class="error">${this.error}</div>` : html``}
+            <range-slider
+                label=${sizeConfig.label}
+                min=${sizeConfig.min}
+                max=${this.maxSize}
+                step=${sizeConfig.step}
+                value=${this.settings?.size || 0}
+                @change=${(event: CustomEvent) => {
+                    event.stopPropagation();
+                    this.updateSettings({ size: event.detail.value });
+                }}
+            ></range-slider>
+        `;
+    }
+}
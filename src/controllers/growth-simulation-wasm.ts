@@ -1,7 +1,7 @@
 import { ReactiveController, ReactiveControllerHost } from 'lit';
 import init, { GrowthSimulation } from 'growth-simulation';
 
-import { Bounds, Config, Initialization, Settings } from '../growth-simulation/config';
+import { Bounds, Config, DensityConfig, ForceField, Initialization, Settings } from '../growth-simulation/config';
 
 interface Vec2 {
     x: number;
@@ -10,9 +10,15 @@ interface Vec2 {
 
 interface Node {
     position: Vec2;
+    velocity: Vec2;
+    fixed: boolean;
+    birth: number;
+    generation: number;
+    /** Always 4 user scalars; `set_node_scalar` throws for other slots. */
+    scalars: number[];
 }
 
-interface Path {
+export interface Path {
     cyclic: boolean;
     nodes: Node[];
 }
@@ -77,6 +83,67 @@ export default class GrowthSimulationWASM implements ReactiveController {
         this.host.requestUpdate();
     }
 
+    updateDensity(newDensity: Partial<DensityConfig>) {
+        if (!this.config) {
+            return;
+        }
+
+        this.config.density = { ...this.config.density, ...newDensity };
+        this.host.requestUpdate();
+    }
+
+    async loadDensityMap(file: Blob) {
+        const bytes = new Uint8Array(await file.arrayBuffer());
+        this.simulation?.load_density_map(bytes);
+    }
+
+    clearDensityMap() {
+        this.simulation?.clear_density_map();
+    }
+
+    addField(field: ForceField): number | undefined {
+        const index = this.simulation?.add_field(field);
+        this.host.requestUpdate();
+        return index;
+    }
+
+    removeField(index: number) {
+        this.simulation?.remove_field(index);
+        this.host.requestUpdate();
+    }
+
+    listFields(): ForceField[] {
+        return this.simulation?.list_fields() ?? [];
+    }
+
+    getPaths(): Path[] {
+        return this.simulation?.get_paths() ?? [];
+    }
+
+    /** Frames since setup; a node's age is this minus its birth. */
+    frame(): number {
+        return Number(this.simulation?.frame() ?? 0);
+    }
+
+    recordedFrameCount(): number {
+        return this.simulation?.recorded_frame_count() ?? 0;
+    }
+
+    /** Recorded frame `index` as a PNG, for saving a numbered sequence. */
+    recordedFramePng(index: number): Blob | undefined {
+        const bytes = this.simulation?.recorded_frame_png(index);
+        return bytes && new Blob([bytes], { type: 'image/png' });
+    }
+
+    recordingToGif(): Blob | undefined {
+        const bytes = this.simulation?.recording_to_gif();
+        return bytes && new Blob([bytes], { type: 'image/gif' });
+    }
+
+    clearRecording() {
+        this.simulation?.clear_recording();
+    }
+
     applyConfig() {
         this.simulation?.update_config(this.config);
     }
@@ -128,7 +195,11 @@ export default class GrowthSimulationWASM implements ReactiveController {
         }
 
         this.applyConfig();
-        this.simulation.setup();
+        try {
+            this.simulation.setup();
+        } catch (err) {
+            console.log('invalid svg path data', err);
+        }
     }
 
     private render() {
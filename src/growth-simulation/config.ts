@@ -11,12 +11,35 @@ export const defaultSettings = {
     max_edge_length: 5.0,
     min_edge_length: 1.0,
     injection_probability: 0.5,
+    injection_strategy: 'Uniform',
+    injection_count: 1,
+    max_neighbors: 0,
+    excluded_neighbors: 0,
+    separation_mode: 'Nodes',
+    wall_mode: 'Freeze',
+    wall_force: 1.0,
+    wall_distance: 10.0,
+    noise_type: 'Curl',
+    noise_strength: 0.0,
+    noise_scale: 0.01,
+    noise_octaves: 3,
+    noise_speed: 0.01,
+    noise_seed: 0,
 };
 
 export type Settings = typeof defaultSettings;
 
+export type CaptureType = 'Vector' | 'Raster';
+
 export const defaultRecordingConfig = {
     recording: false,
+    // Captures every `interval` frames.
+    interval: 1,
+    capture_type: 'Vector' as CaptureType,
+    // 0 records without a limit.
+    max_frames: 0,
+    // How long each frame shows in an exported GIF, in milliseconds.
+    frame_delay: 40,
 };
 
 export type RecordingConfig = typeof defaultRecordingConfig;
@@ -35,9 +58,17 @@ export const defaultPolygonConfig = {
 
 export type PolygonConfig = typeof defaultPolygonConfig;
 
+export const defaultSvgConfig = {
+    path_data: '',
+    size: 300.0,
+};
+
+export type SvgConfig = typeof defaultSvgConfig;
+
 export const defaultInitialization = {
     init_type: 'Polygon',
     polygon_config: defaultPolygonConfig,
+    svg_config: defaultSvgConfig,
 };
 
 export type Initialization = typeof defaultInitialization;
@@ -55,20 +86,96 @@ export const defaultCircleBoundsConfig = {
 
 export type CircleConfig = typeof defaultCircleBoundsConfig;
 
+export type Point = { x: number; y: number };
+
+export const defaultPolygonBoundsConfig = {
+    vertices: [] as Point[],
+    holes: [] as Point[][],
+};
+
+export type PolygonBoundsConfig = typeof defaultPolygonBoundsConfig;
+
+// Mirrors the Rust `BoundsShape` enum, e.g.
+// `{ Difference: [{ Circle: { radius: 150 } }, { Circle: { radius: 75 } }] }`.
+export type BoundsShape =
+    | 'View'
+    | { Rect: RectConfig }
+    | { Circle: CircleConfig }
+    | { Polygon: PolygonBoundsConfig }
+    | { Svg: SvgConfig }
+    | { Union: BoundsShape[] }
+    | { Intersection: BoundsShape[] }
+    | { Difference: [BoundsShape, BoundsShape] }
+    | { Invert: BoundsShape };
+
 export const defaultBounds = {
     bounds_type: 'View',
     draw: false,
     circle_config: defaultCircleBoundsConfig,
     rect_config: defaultRectBoundsConfig,
+    polygon_config: defaultPolygonBoundsConfig,
+    svg_config: defaultSvgConfig,
+    composite: 'View' as BoundsShape,
 };
 
 export type Bounds = typeof defaultBounds;
 
+// Applied while a density map image is loaded.
+export const defaultDensityConfig = {
+    dark_scale: 0.25,
+    scale_edge_length: true,
+    scale_separation: true,
+    scale_injection: true,
+};
+
+export type DensityConfig = typeof defaultDensityConfig;
+
+// Mirrors the Rust `ForceField`, managed through the simulation rather than
+// the config.
+export type FieldKind = 'Attractor' | 'Repeller' | { Flow: { direction: Point } };
+
+export type Falloff = 'Constant' | 'Linear' | 'Quadratic';
+
+export interface ForceField {
+    kind: FieldKind;
+    position: Point;
+    strength: number;
+    radius: number;
+    falloff?: Falloff;
+}
+
+export type StyleSource =
+    | 'ArcLength'
+    | 'Age'
+    | 'Generation'
+    | 'Curvature'
+    | 'Velocity'
+    | 'Density'
+    | { Scalar: number };
+
+export const defaultStyleConfig = {
+    path_colors: ['#ffffff'],
+    color_by: null as StyleSource | null,
+    palette: ['#440154', '#21918c', '#fde725'],
+    line_width: 1.0,
+    width_by: null as StyleSource | null,
+    min_width: 0.5,
+    max_width: 3.0,
+    density_radius: 20,
+};
+
+export type StyleConfig = typeof defaultStyleConfig;
+
 export const defaultConfig = {
     settings: defaultSettings,
     initialization: defaultInitialization,
     bounds: defaultBounds,
+    density: defaultDensityConfig,
+    injection_mask: null as BoundsShape | null,
+    style: defaultStyleConfig,
     recording: defaultRecordingConfig,
+    seed: 0,
+    spatial_index: 'Grid',
 };
 
 export type Config = typeof defaultConfig;